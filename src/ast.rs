@@ -1,17 +1,58 @@
-use crate::token::Token;
+use crate::token::{Span, Token};
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+pub mod printer;
+
+#[derive(Debug, Clone, Serialize)]
 pub enum LitralValue {
     NUMBER(f64),
-    STRING(String),
+    /// Shared by the string values the literal evaluates to.
+    STRING(Arc<str>),
     True,
     False,
     Nil,
 }
 
-#[derive(Clone, Debug)]
+/// Written the way it appears in Lox source.
+impl fmt::Display for LitralValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LitralValue::NUMBER(value) => write!(f, "{}", value),
+            LitralValue::STRING(value) => write!(f, "\"{}\"", escape(value)),
+            LitralValue::True => write!(f, "true"),
+            LitralValue::False => write!(f, "false"),
+            LitralValue::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// Escapes the charactors which can't appear as is inside a string literal.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub enum Expr {
-    Litral(LitralValue),
+    Litral {
+        value: LitralValue,
+        span: Span,
+    },
     Variable {
         name: Token,
         depth: Option<usize>,
@@ -39,14 +80,41 @@ pub enum Expr {
         operator: Token,
         right: Box<Expr>,
     },
+    /// `condition ? then_branch : else_branch`
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    /// `left ?? right`, `right` is evaluated only when `left` is nil.
+    Coalesce {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
     Grouping {
         expression: Box<Expr>,
+        /// From the opening to the closing paranthesis.
+        span: Span,
     },
     Assign {
         name: Token,
         depth: Option<usize>,
         value: Box<Expr>,
     },
+    /// `target += value` and the like. `target` is a `Variable`, `Get` or `Index`, whose
+    /// object and index are evaluated once.
+    CompoundAssign {
+        target: Box<Expr>,
+        operator: Token,
+        value: Box<Expr>,
+    },
+    /// `++target`, `target++`, `--target` or `target--`, `target` is as in `CompoundAssign`.
+    Increment {
+        target: Box<Expr>,
+        operator: Token,
+        prefix: bool,
+    },
     Call {
         callee: Box<Expr>,
         paran: Token,
@@ -61,23 +129,63 @@ pub enum Expr {
         name: Token,
         value: Box<Expr>,
     },
+    Lambda {
+        keyword: Token,
+        fun: Arc<Fun>,
+        /// From the `fun` keyword to the closing brace.
+        span: Span,
+    },
+    /// A string literal with `${expr}` in it.
+    /// `parts` alternate between string literal segments and the interpolated expressions,
+    /// starting and ending with a segment, which may be empty.
+    Interpolate {
+        parts: Vec<Expr>,
+        span: Span,
+    },
+    List {
+        elements: Vec<Expr>,
+        /// From the opening to the closing bracket.
+        span: Span,
+    },
+    Map {
+        entries: Vec<(Expr, Expr)>,
+        /// The closing brace.
+        brace: Token,
+        /// From the opening to the closing brace.
+        span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        /// The closing bracket.
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
 }
 
-#[derive(Clone, Debug)]
+/// Declaration of a function, method or lambda. Held behind an `Arc` so the functions
+/// created from it at runtime share it.
+#[derive(Clone, Debug, Serialize)]
 pub struct Fun {
-    pub name: Token,
+    /// `None` for lambdas.
+    pub name: Option<Token>,
     pub params: Vec<Token>,
     pub body: Vec<Stmt>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum Stmt {
     Class {
         name: Token,
         super_class: Option<Expr>,
-        methods: Vec<Fun>,
+        methods: Vec<Arc<Fun>>,
     },
-    Function(Fun),
+    Function(Arc<Fun>),
     Var {
         name: Token,
         expression: Option<Expr>,
@@ -99,9 +207,716 @@ pub enum Stmt {
     WhileStmt {
         condition: Expr,
         body: Box<Stmt>,
+        /// Increment of a `for` loop, evaluated after the body even when it `continue`s.
+        increment: Option<Expr>,
     },
     Return {
         keyword: Token,
         value: Option<Expr>,
     },
+    Break {
+        keyword: Token,
+    },
+    Continue {
+        keyword: Token,
+    },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    /// At least one of `catch` and `finally` is present.
+    Try {
+        keyword: Token,
+        body: Vec<Stmt>,
+        catch: Option<Catch>,
+        finally: Option<Vec<Stmt>>,
+    },
+}
+
+/// `catch (name) { body }` clause of a `try` statement.
+#[derive(Clone, Debug, Serialize)]
+pub struct Catch {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}
+
+impl Expr {
+    /// Source span covered by this expression.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Litral { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Lambda { span, .. }
+            | Expr::Interpolate { span, .. }
+            | Expr::List { span, .. }
+            | Expr::Map { span, .. } => span.clone(),
+            Expr::Variable { name, .. } => name.span.clone(),
+            Expr::This { keyword, .. } => keyword.span.clone(),
+            Expr::Super {
+                keyword, method, ..
+            } => keyword.span.to(&method.span),
+            Expr::Unary { operator, right } => operator.span.to(&right.span()),
+            Expr::Binary { left, right, .. }
+            | Expr::Logical { left, right, .. }
+            | Expr::Coalesce { left, right, .. } => left.span().to(&right.span()),
+            Expr::Conditional {
+                condition,
+                else_branch,
+                ..
+            } => condition.span().to(&else_branch.span()),
+            Expr::Assign { name, value, .. } => name.span.to(&value.span()),
+            Expr::CompoundAssign { target, value, .. } => target.span().to(&value.span()),
+            Expr::Increment {
+                target,
+                operator,
+                prefix: true,
+            } => operator.span.to(&target.span()),
+            Expr::Increment {
+                target,
+                operator,
+                prefix: false,
+            } => target.span().to(&operator.span),
+            Expr::Call { callee, paran, .. } => callee.span().to(&paran.span),
+            Expr::Get { object, name } => object.span().to(&name.span),
+            Expr::Set { object, value, .. } | Expr::IndexSet { object, value, .. } => {
+                object.span().to(&value.span())
+            }
+            Expr::Index {
+                object, bracket, ..
+            } => object.span().to(&bracket.span),
+        }
+    }
+}
+
+impl Stmt {
+    /// Source span covered by this statement.
+    /// `Note:` Keywords which are not kept in the tree (eg. `print`, `if`) are not covered.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Class { name, .. } => name.span.clone(),
+            Stmt::Function(fun) => fun
+                .name
+                .as_ref()
+                .map_or_else(Span::default, |name| name.span.clone()),
+            Stmt::Var { name, expression } => match expression {
+                Some(expression) => name.span.to(&expression.span()),
+                None => name.span.clone(),
+            },
+            Stmt::PrintStmt { expression } | Stmt::ExpressionStmt { expression } => {
+                expression.span()
+            }
+            Stmt::Block { statements } => match (statements.first(), statements.last()) {
+                (Some(first), Some(last)) => first.span().to(&last.span()),
+                _ => Span::default(),
+            },
+            Stmt::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => match else_branch {
+                Some(else_branch) => condition.span().to(&else_branch.span()),
+                None => condition.span().to(&then_branch.span()),
+            },
+            Stmt::WhileStmt {
+                condition, body, ..
+            } => condition.span().to(&body.span()),
+            Stmt::Return { keyword, value } => match value {
+                Some(value) => keyword.span.to(&value.span()),
+                None => keyword.span.clone(),
+            },
+            Stmt::Break { keyword } | Stmt::Continue { keyword } => keyword.span.clone(),
+            Stmt::Throw { keyword, value } => keyword.span.to(&value.span()),
+            Stmt::Try { keyword, .. } => keyword.span.clone(),
+        }
+    }
+}
+
+/// Read only traversal of the tree.
+/// Every method defaults to visiting the children through the matching `walk_*` function,
+/// so an implementation only overrides the nodes it is interested in and calls `walk_*`
+/// from the override to keep descending.
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_fun(&mut self, fun: &Fun) {
+        walk_fun(self, fun)
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Class {
+            super_class,
+            methods,
+            ..
+        } => {
+            if let Some(super_class) = super_class {
+                visitor.visit_expr(super_class);
+            }
+            methods.iter().for_each(|method| visitor.visit_fun(method));
+        }
+        Stmt::Function(fun) => visitor.visit_fun(fun),
+        Stmt::Var { expression, .. } => {
+            if let Some(expression) = expression {
+                visitor.visit_expr(expression);
+            }
+        }
+        Stmt::PrintStmt { expression } | Stmt::ExpressionStmt { expression } => {
+            visitor.visit_expr(expression)
+        }
+        Stmt::Block { statements } => statements.iter().for_each(|stmt| visitor.visit_stmt(stmt)),
+        Stmt::IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        Stmt::WhileStmt {
+            condition,
+            body,
+            increment,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+            if let Some(increment) = increment {
+                visitor.visit_expr(increment);
+            }
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        Stmt::Throw { value, .. } => visitor.visit_expr(value),
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            body.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+            if let Some(catch) = catch {
+                catch.body.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+            }
+            if let Some(finally) = finally {
+                finally.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+            }
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Litral { .. } | Expr::Variable { .. } | Expr::This { .. } | Expr::Super { .. } => {}
+        Expr::Unary { right, .. } => visitor.visit_expr(right),
+        Expr::Binary { left, right, .. }
+        | Expr::Logical { left, right, .. }
+        | Expr::Coalesce { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_branch);
+            visitor.visit_expr(else_branch);
+        }
+        Expr::Grouping { expression, .. } => visitor.visit_expr(expression),
+        Expr::Assign { value, .. } => visitor.visit_expr(value),
+        Expr::CompoundAssign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        Expr::Increment { target, .. } => visitor.visit_expr(target),
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expr(callee);
+            arguments
+                .iter()
+                .for_each(|argument| visitor.visit_expr(argument));
+        }
+        Expr::Get { object, .. } => visitor.visit_expr(object),
+        Expr::Set { object, value, .. } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(value);
+        }
+        Expr::Lambda { fun, .. } => visitor.visit_fun(fun),
+        Expr::Interpolate {
+            parts: elements, ..
+        }
+        | Expr::List { elements, .. } => elements
+            .iter()
+            .for_each(|element| visitor.visit_expr(element)),
+        Expr::Map { entries, .. } => entries.iter().for_each(|(key, value)| {
+            visitor.visit_expr(key);
+            visitor.visit_expr(value);
+        }),
+        Expr::Index { object, index, .. } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(index);
+        }
+        Expr::IndexSet {
+            object,
+            index,
+            value,
+            ..
+        } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(index);
+            visitor.visit_expr(value);
+        }
+    }
+}
+
+pub fn walk_fun<V: Visitor + ?Sized>(visitor: &mut V, fun: &Fun) {
+    fun.body.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+}
+
+/// Like `Visitor` but can modify the nodes in place, eg. to fill in the resolved `depth`.
+pub trait VisitorMut {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_fun_mut(&mut self, fun: &mut Fun) {
+        walk_fun_mut(self, fun)
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Class {
+            super_class,
+            methods,
+            ..
+        } => {
+            if let Some(super_class) = super_class {
+                visitor.visit_expr_mut(super_class);
+            }
+            methods
+                .iter_mut()
+                .for_each(|method| visitor.visit_fun_mut(Arc::make_mut(method)));
+        }
+        Stmt::Function(fun) => visitor.visit_fun_mut(Arc::make_mut(fun)),
+        Stmt::Var { expression, .. } => {
+            if let Some(expression) = expression {
+                visitor.visit_expr_mut(expression);
+            }
+        }
+        Stmt::PrintStmt { expression } | Stmt::ExpressionStmt { expression } => {
+            visitor.visit_expr_mut(expression)
+        }
+        Stmt::Block { statements } => statements
+            .iter_mut()
+            .for_each(|stmt| visitor.visit_stmt_mut(stmt)),
+        Stmt::IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt_mut(else_branch);
+            }
+        }
+        Stmt::WhileStmt {
+            condition,
+            body,
+            increment,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+            if let Some(increment) = increment {
+                visitor.visit_expr_mut(increment);
+            }
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        Stmt::Throw { value, .. } => visitor.visit_expr_mut(value),
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            body.iter_mut()
+                .for_each(|stmt| visitor.visit_stmt_mut(stmt));
+            if let Some(catch) = catch {
+                catch
+                    .body
+                    .iter_mut()
+                    .for_each(|stmt| visitor.visit_stmt_mut(stmt));
+            }
+            if let Some(finally) = finally {
+                finally
+                    .iter_mut()
+                    .for_each(|stmt| visitor.visit_stmt_mut(stmt));
+            }
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Litral { .. } | Expr::Variable { .. } | Expr::This { .. } | Expr::Super { .. } => {}
+        Expr::Unary { right, .. } => visitor.visit_expr_mut(right),
+        Expr::Binary { left, right, .. }
+        | Expr::Logical { left, right, .. }
+        | Expr::Coalesce { left, right, .. } => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(then_branch);
+            visitor.visit_expr_mut(else_branch);
+        }
+        Expr::Grouping { expression, .. } => visitor.visit_expr_mut(expression),
+        Expr::Assign { value, .. } => visitor.visit_expr_mut(value),
+        Expr::CompoundAssign { target, value, .. } => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        Expr::Increment { target, .. } => visitor.visit_expr_mut(target),
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expr_mut(callee);
+            arguments
+                .iter_mut()
+                .for_each(|argument| visitor.visit_expr_mut(argument));
+        }
+        Expr::Get { object, .. } => visitor.visit_expr_mut(object),
+        Expr::Set { object, value, .. } => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_expr_mut(value);
+        }
+        Expr::Lambda { fun, .. } => visitor.visit_fun_mut(Arc::make_mut(fun)),
+        Expr::Interpolate {
+            parts: elements, ..
+        }
+        | Expr::List { elements, .. } => elements
+            .iter_mut()
+            .for_each(|element| visitor.visit_expr_mut(element)),
+        Expr::Map { entries, .. } => entries.iter_mut().for_each(|(key, value)| {
+            visitor.visit_expr_mut(key);
+            visitor.visit_expr_mut(value);
+        }),
+        Expr::Index { object, index, .. } => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_expr_mut(index);
+        }
+        Expr::IndexSet {
+            object,
+            index,
+            value,
+            ..
+        } => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_expr_mut(index);
+            visitor.visit_expr_mut(value);
+        }
+    }
+}
+
+pub fn walk_fun_mut<V: VisitorMut + ?Sized>(visitor: &mut V, fun: &mut Fun) {
+    fun.body
+        .iter_mut()
+        .for_each(|stmt| visitor.visit_stmt_mut(stmt));
+}
+
+/// Transforms the tree by value, rebuilding every node from its folded children.
+/// The free `fold_*` functions do the rebuilding, so an override can replace a node
+/// outright or call them to fold the children first.
+pub trait Fold {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_fun(&mut self, fun: Fun) -> Fun {
+        fold_fun(self, fun)
+    }
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Class {
+            name,
+            super_class,
+            methods,
+        } => Stmt::Class {
+            name,
+            super_class: super_class.map(|super_class| folder.fold_expr(super_class)),
+            methods: methods
+                .into_iter()
+                .map(|method| Arc::new(folder.fold_fun(Arc::unwrap_or_clone(method))))
+                .collect(),
+        },
+        Stmt::Function(fun) => Stmt::Function(Arc::new(folder.fold_fun(Arc::unwrap_or_clone(fun)))),
+        Stmt::Var { name, expression } => Stmt::Var {
+            name,
+            expression: expression.map(|expression| folder.fold_expr(expression)),
+        },
+        Stmt::PrintStmt { expression } => Stmt::PrintStmt {
+            expression: folder.fold_expr(expression),
+        },
+        Stmt::ExpressionStmt { expression } => Stmt::ExpressionStmt {
+            expression: folder.fold_expr(expression),
+        },
+        Stmt::Block { statements } => Stmt::Block {
+            statements: statements
+                .into_iter()
+                .map(|stmt| folder.fold_stmt(stmt))
+                .collect(),
+        },
+        Stmt::IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        } => Stmt::IfStmt {
+            condition: folder.fold_expr(condition),
+            then_branch: Box::new(folder.fold_stmt(*then_branch)),
+            else_branch: else_branch.map(|else_branch| Box::new(folder.fold_stmt(*else_branch))),
+        },
+        Stmt::WhileStmt {
+            condition,
+            body,
+            increment,
+        } => Stmt::WhileStmt {
+            condition: folder.fold_expr(condition),
+            body: Box::new(folder.fold_stmt(*body)),
+            increment: increment.map(|increment| folder.fold_expr(increment)),
+        },
+        Stmt::Return { keyword, value } => Stmt::Return {
+            keyword,
+            value: value.map(|value| folder.fold_expr(value)),
+        },
+        Stmt::Break { .. } | Stmt::Continue { .. } => stmt,
+        Stmt::Throw { keyword, value } => Stmt::Throw {
+            keyword,
+            value: folder.fold_expr(value),
+        },
+        Stmt::Try {
+            keyword,
+            body,
+            catch,
+            finally,
+        } => Stmt::Try {
+            keyword,
+            body: body
+                .into_iter()
+                .map(|stmt| folder.fold_stmt(stmt))
+                .collect(),
+            catch: catch.map(|catch| Catch {
+                name: catch.name,
+                body: catch
+                    .body
+                    .into_iter()
+                    .map(|stmt| folder.fold_stmt(stmt))
+                    .collect(),
+            }),
+            finally: finally.map(|finally| {
+                finally
+                    .into_iter()
+                    .map(|stmt| folder.fold_stmt(stmt))
+                    .collect()
+            }),
+        },
+    }
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Litral { .. } | Expr::Variable { .. } | Expr::This { .. } | Expr::Super { .. } => {
+            expr
+        }
+        Expr::Unary { operator, right } => Expr::Unary {
+            operator,
+            right: Box::new(folder.fold_expr(*right)),
+        },
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => Expr::Binary {
+            left: Box::new(folder.fold_expr(*left)),
+            operator,
+            right: Box::new(folder.fold_expr(*right)),
+        },
+        Expr::Logical {
+            left,
+            operator,
+            right,
+        } => Expr::Logical {
+            left: Box::new(folder.fold_expr(*left)),
+            operator,
+            right: Box::new(folder.fold_expr(*right)),
+        },
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => Expr::Conditional {
+            condition: Box::new(folder.fold_expr(*condition)),
+            then_branch: Box::new(folder.fold_expr(*then_branch)),
+            else_branch: Box::new(folder.fold_expr(*else_branch)),
+        },
+        Expr::Coalesce {
+            left,
+            operator,
+            right,
+        } => Expr::Coalesce {
+            left: Box::new(folder.fold_expr(*left)),
+            operator,
+            right: Box::new(folder.fold_expr(*right)),
+        },
+        Expr::Grouping { expression, span } => Expr::Grouping {
+            expression: Box::new(folder.fold_expr(*expression)),
+            span,
+        },
+        Expr::Assign { name, depth, value } => Expr::Assign {
+            name,
+            depth,
+            value: Box::new(folder.fold_expr(*value)),
+        },
+        Expr::CompoundAssign {
+            target,
+            operator,
+            value,
+        } => Expr::CompoundAssign {
+            target: Box::new(folder.fold_expr(*target)),
+            operator,
+            value: Box::new(folder.fold_expr(*value)),
+        },
+        Expr::Increment {
+            target,
+            operator,
+            prefix,
+        } => Expr::Increment {
+            target: Box::new(folder.fold_expr(*target)),
+            operator,
+            prefix,
+        },
+        Expr::Call {
+            callee,
+            paran,
+            arguments,
+        } => Expr::Call {
+            callee: Box::new(folder.fold_expr(*callee)),
+            paran,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expr(argument))
+                .collect(),
+        },
+        Expr::Get { object, name } => Expr::Get {
+            object: Box::new(folder.fold_expr(*object)),
+            name,
+        },
+        Expr::Set {
+            object,
+            name,
+            value,
+        } => Expr::Set {
+            object: Box::new(folder.fold_expr(*object)),
+            name,
+            value: Box::new(folder.fold_expr(*value)),
+        },
+        Expr::Lambda { keyword, fun, span } => Expr::Lambda {
+            keyword,
+            fun: Arc::new(folder.fold_fun(Arc::unwrap_or_clone(fun))),
+            span,
+        },
+        Expr::Interpolate { parts, span } => Expr::Interpolate {
+            parts: parts
+                .into_iter()
+                .map(|part| folder.fold_expr(part))
+                .collect(),
+            span,
+        },
+        Expr::List { elements, span } => Expr::List {
+            elements: elements
+                .into_iter()
+                .map(|element| folder.fold_expr(element))
+                .collect(),
+            span,
+        },
+        Expr::Map {
+            entries,
+            brace,
+            span,
+        } => Expr::Map {
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| (folder.fold_expr(key), folder.fold_expr(value)))
+                .collect(),
+            brace,
+            span,
+        },
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => Expr::Index {
+            object: Box::new(folder.fold_expr(*object)),
+            bracket,
+            index: Box::new(folder.fold_expr(*index)),
+        },
+        Expr::IndexSet {
+            object,
+            bracket,
+            index,
+            value,
+        } => Expr::IndexSet {
+            object: Box::new(folder.fold_expr(*object)),
+            bracket,
+            index: Box::new(folder.fold_expr(*index)),
+            value: Box::new(folder.fold_expr(*value)),
+        },
+    }
+}
+
+pub fn fold_fun<F: Fold + ?Sized>(folder: &mut F, fun: Fun) -> Fun {
+    Fun {
+        name: fun.name,
+        params: fun.params,
+        body: fun
+            .body
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+    }
 }
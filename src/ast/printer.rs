@@ -0,0 +1,771 @@
+use super::{escape, Expr, Fun, LitralValue, Stmt};
+use crate::token::{Token, TokenType};
+
+/// Prints the tree in parenthesized prefix form, e.g. `(* (- 123) (group 45.67))`.
+pub struct AstPrinter;
+
+impl AstPrinter {
+    /// One line per statement.
+    pub fn print(stmts: &[Stmt]) -> String {
+        stmts
+            .iter()
+            .map(Self::print_stmt)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn print_stmt(stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                let mut parts = vec![String::from("class"), name.lexeme.clone()];
+                if let Some(super_class) = super_class {
+                    parts.push(format!("(< {})", Self::print_expr(super_class)));
+                }
+                parts.extend(methods.iter().map(|method| Self::print_fun(method)));
+                Self::parenthesize(&parts)
+            }
+            Stmt::Function(fun) => Self::print_fun(fun),
+            Stmt::Var { name, expression } => {
+                let mut parts = vec![String::from("var"), name.lexeme.clone()];
+                if let Some(expression) = expression {
+                    parts.push(Self::print_expr(expression));
+                }
+                Self::parenthesize(&parts)
+            }
+            Stmt::PrintStmt { expression } => {
+                Self::parenthesize(&[String::from("print"), Self::print_expr(expression)])
+            }
+            Stmt::ExpressionStmt { expression } => {
+                Self::parenthesize(&[String::from(";"), Self::print_expr(expression)])
+            }
+            Stmt::Block { statements } => Self::print_block(statements),
+            Stmt::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let mut parts = vec![
+                    String::from("if"),
+                    Self::print_expr(condition),
+                    Self::print_stmt(then_branch),
+                ];
+                if let Some(else_branch) = else_branch {
+                    parts.push(Self::print_stmt(else_branch));
+                }
+                Self::parenthesize(&parts)
+            }
+            Stmt::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => match increment {
+                Some(increment) => Self::parenthesize(&[
+                    String::from("for"),
+                    Self::print_expr(condition),
+                    Self::print_expr(increment),
+                    Self::print_stmt(body),
+                ]),
+                None => Self::parenthesize(&[
+                    String::from("while"),
+                    Self::print_expr(condition),
+                    Self::print_stmt(body),
+                ]),
+            },
+            Stmt::Return { value, .. } => {
+                let mut parts = vec![String::from("return")];
+                if let Some(value) = value {
+                    parts.push(Self::print_expr(value));
+                }
+                Self::parenthesize(&parts)
+            }
+            Stmt::Break { .. } => String::from("(break)"),
+            Stmt::Continue { .. } => String::from("(continue)"),
+            Stmt::Throw { value, .. } => {
+                Self::parenthesize(&[String::from("throw"), Self::print_expr(value)])
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                let mut parts = vec![String::from("try"), Self::print_block(body)];
+                if let Some(catch) = catch {
+                    parts.push(Self::parenthesize(&[
+                        String::from("catch"),
+                        catch.name.lexeme.clone(),
+                        Self::print_block(&catch.body),
+                    ]));
+                }
+                if let Some(finally) = finally {
+                    parts.push(Self::parenthesize(&[
+                        String::from("finally"),
+                        Self::print_block(finally),
+                    ]));
+                }
+                Self::parenthesize(&parts)
+            }
+        }
+    }
+
+    pub fn print_expr(expr: &Expr) -> String {
+        match expr {
+            Expr::Litral { value, .. } => value.to_string(),
+            Expr::Variable { name, .. } => name.lexeme.clone(),
+            Expr::This { .. } => String::from("this"),
+            Expr::Super { method, .. } => {
+                Self::parenthesize(&[String::from("super"), method.lexeme.clone()])
+            }
+            Expr::Unary { operator, right } => {
+                Self::parenthesize(&[operator.lexeme.clone(), Self::print_expr(right)])
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Coalesce {
+                left,
+                operator,
+                right,
+            } => Self::parenthesize(&[
+                operator.lexeme.clone(),
+                Self::print_expr(left),
+                Self::print_expr(right),
+            ]),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => Self::parenthesize(&[
+                String::from("?:"),
+                Self::print_expr(condition),
+                Self::print_expr(then_branch),
+                Self::print_expr(else_branch),
+            ]),
+            Expr::Grouping { expression, .. } => {
+                Self::parenthesize(&[String::from("group"), Self::print_expr(expression)])
+            }
+            Expr::Assign { name, value, .. } => Self::parenthesize(&[
+                String::from("="),
+                name.lexeme.clone(),
+                Self::print_expr(value),
+            ]),
+            Expr::CompoundAssign {
+                target,
+                operator,
+                value,
+            } => Self::parenthesize(&[
+                operator.lexeme.clone(),
+                Self::print_expr(target),
+                Self::print_expr(value),
+            ]),
+            Expr::Increment {
+                target,
+                operator,
+                prefix,
+            } => Self::parenthesize(&[
+                String::from(if *prefix { "prefix" } else { "postfix" }),
+                operator.lexeme.clone(),
+                Self::print_expr(target),
+            ]),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut parts = vec![String::from("call"), Self::print_expr(callee)];
+                parts.extend(arguments.iter().map(Self::print_expr));
+                Self::parenthesize(&parts)
+            }
+            Expr::Get { object, name } => Self::parenthesize(&[
+                String::from("."),
+                Self::print_expr(object),
+                name.lexeme.clone(),
+            ]),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => Self::parenthesize(&[
+                String::from("="),
+                Self::parenthesize(&[
+                    String::from("."),
+                    Self::print_expr(object),
+                    name.lexeme.clone(),
+                ]),
+                Self::print_expr(value),
+            ]),
+            Expr::Lambda { fun, .. } => Self::print_fun(fun),
+            Expr::Interpolate { parts, .. } => {
+                let mut parts: Vec<String> = parts.iter().map(Self::print_expr).collect();
+                parts.insert(0, String::from("interpolate"));
+                Self::parenthesize(&parts)
+            }
+            Expr::List { elements, .. } => {
+                let mut parts = vec![String::from("list")];
+                parts.extend(elements.iter().map(Self::print_expr));
+                Self::parenthesize(&parts)
+            }
+            Expr::Map { entries, .. } => {
+                let mut parts = vec![String::from("map")];
+                parts.extend(entries.iter().map(|(key, value)| {
+                    Self::parenthesize(&[Self::print_expr(key), Self::print_expr(value)])
+                }));
+                Self::parenthesize(&parts)
+            }
+            Expr::Index { object, index, .. } => Self::parenthesize(&[
+                String::from("index"),
+                Self::print_expr(object),
+                Self::print_expr(index),
+            ]),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => Self::parenthesize(&[
+                String::from("="),
+                Self::parenthesize(&[
+                    String::from("index"),
+                    Self::print_expr(object),
+                    Self::print_expr(index),
+                ]),
+                Self::print_expr(value),
+            ]),
+        }
+    }
+
+    fn print_block(statements: &[Stmt]) -> String {
+        let mut parts = vec![String::from("block")];
+        parts.extend(statements.iter().map(Self::print_stmt));
+        Self::parenthesize(&parts)
+    }
+
+    /// `(fun name (params) body...)`, the name is left out for lambdas.
+    fn print_fun(fun: &Fun) -> String {
+        let params: Vec<String> = fun.params.iter().map(|p| p.lexeme.clone()).collect();
+        let mut parts = vec![String::from("fun")];
+        if let Some(name) = &fun.name {
+            parts.push(name.lexeme.clone());
+        }
+        parts.push(Self::parenthesize(&params));
+        parts.extend(fun.body.iter().map(Self::print_stmt));
+        Self::parenthesize(&parts)
+    }
+
+    fn parenthesize(parts: &[String]) -> String {
+        format!("({})", parts.join(" "))
+    }
+}
+
+/// Binding power of expressions, mirrors the grammar in `Parser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Assignment,
+    Conditional,
+    Coalesce,
+    Or,
+    And,
+    Equality,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Power,
+    Postfix,
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    fn of_operator(operator: &Token) -> Precedence {
+        match operator.token_type {
+            TokenType::QUESTION_QUESTION => Precedence::Coalesce,
+            TokenType::OR => Precedence::Or,
+            TokenType::AND => Precedence::And,
+            TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL => Precedence::Equality,
+            TokenType::PIPE => Precedence::BitOr,
+            TokenType::CARET => Precedence::BitXor,
+            TokenType::AMPERSAND => Precedence::BitAnd,
+            TokenType::LESS_LESS | TokenType::GREATER_GREATER => Precedence::Shift,
+            TokenType::GREATER
+            | TokenType::GREATER_EQUAL
+            | TokenType::LESS
+            | TokenType::LESS_EQUAL => Precedence::Comparison,
+            TokenType::MINUS | TokenType::PLUS => Precedence::Term,
+            TokenType::STAR_STAR => Precedence::Power,
+            _ => Precedence::Factor,
+        }
+    }
+
+    fn of(expr: &Expr) -> Precedence {
+        match expr {
+            Expr::Assign { .. }
+            | Expr::Set { .. }
+            | Expr::IndexSet { .. }
+            | Expr::CompoundAssign { .. } => Precedence::Assignment,
+            Expr::Increment { prefix: true, .. } => Precedence::Unary,
+            Expr::Increment { prefix: false, .. } => Precedence::Postfix,
+            Expr::Conditional { .. } => Precedence::Conditional,
+            Expr::Binary { operator, .. }
+            | Expr::Logical { operator, .. }
+            | Expr::Coalesce { operator, .. } => Self::of_operator(operator),
+            Expr::Unary { .. } => Precedence::Unary,
+            Expr::Call { .. } | Expr::Get { .. } | Expr::Index { .. } => Precedence::Call,
+            Expr::Litral { .. }
+            | Expr::Variable { .. }
+            | Expr::This { .. }
+            | Expr::Super { .. }
+            | Expr::Grouping { .. }
+            | Expr::Lambda { .. }
+            | Expr::Interpolate { .. }
+            | Expr::List { .. }
+            | Expr::Map { .. } => Precedence::Primary,
+        }
+    }
+
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Coalesce,
+            Precedence::Coalesce => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::BitOr,
+            Precedence::BitOr => Precedence::BitXor,
+            Precedence::BitXor => Precedence::BitAnd,
+            Precedence::BitAnd => Precedence::Shift,
+            Precedence::Shift => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Power,
+            Precedence::Power => Precedence::Postfix,
+            Precedence::Postfix => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+/// Prints the tree back as Lox source.
+/// Desugared constructs are printed in their desugared form, e.g. a `for` loop comes
+/// back as a block holding the initializer and a `for` loop without one. Parentheses are
+/// added where the tree needs them and branches are always printed as blocks, so parsing
+/// the output and printing it again gives the same output.
+pub struct SourcePrinter;
+
+const INDENT: &str = "    ";
+
+impl SourcePrinter {
+    pub fn print(stmts: &[Stmt]) -> String {
+        let mut out = String::new();
+        stmts
+            .iter()
+            .for_each(|stmt| Self::write_stmt(&mut out, stmt, 0));
+        out
+    }
+
+    pub fn print_stmt(stmt: &Stmt) -> String {
+        let mut out = String::new();
+        Self::write_stmt(&mut out, stmt, 0);
+        out
+    }
+
+    pub fn print_expr(expr: &Expr) -> String {
+        Self::expr(expr, Precedence::Assignment, 0)
+    }
+
+    fn write_stmt(out: &mut String, stmt: &Stmt, depth: usize) {
+        out.push_str(&INDENT.repeat(depth));
+        Self::write_stmt_body(out, stmt, depth);
+        out.push('\n');
+    }
+
+    /// Writes `stmt` without the leading indentation and the trailing new line.
+    fn write_stmt_body(out: &mut String, stmt: &Stmt, depth: usize) {
+        match stmt {
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                out.push_str(&format!("class {}", name.lexeme));
+                if let Some(super_class) = super_class {
+                    out.push_str(&format!(" < {}", Self::expr_at(super_class, depth)));
+                }
+                out.push_str(" {\n");
+                methods.iter().for_each(|method| {
+                    out.push_str(&INDENT.repeat(depth + 1));
+                    Self::write_fun(out, method, depth + 1);
+                    out.push('\n');
+                });
+                out.push_str(&INDENT.repeat(depth));
+                out.push('}');
+            }
+            Stmt::Function(fun) => {
+                out.push_str("fun ");
+                Self::write_fun(out, fun, depth);
+            }
+            Stmt::Var { name, expression } => match expression {
+                Some(expression) => out.push_str(&format!(
+                    "var {} = {};",
+                    name.lexeme,
+                    Self::expr_at(expression, depth)
+                )),
+                None => out.push_str(&format!("var {};", name.lexeme)),
+            },
+            Stmt::PrintStmt { expression } => {
+                out.push_str(&format!("print {};", Self::expr_at(expression, depth)))
+            }
+            Stmt::ExpressionStmt { expression } => {
+                out.push_str(&format!("{};", Self::expr_at(expression, depth)))
+            }
+            Stmt::Block { statements } => Self::write_block(out, statements, depth),
+            Stmt::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                out.push_str(&format!("if ({}) ", Self::expr_at(condition, depth)));
+                Self::write_branch(out, then_branch, depth);
+                if let Some(else_branch) = else_branch {
+                    out.push_str(" else ");
+                    Self::write_branch(out, else_branch, depth);
+                }
+            }
+            Stmt::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => {
+                match increment {
+                    // Only a `for` loop has an increment.
+                    Some(increment) => out.push_str(&format!(
+                        "for (; {}; {}) ",
+                        Self::expr_at(condition, depth),
+                        Self::expr_at(increment, depth)
+                    )),
+                    None => out.push_str(&format!("while ({}) ", Self::expr_at(condition, depth))),
+                }
+                Self::write_branch(out, body, depth);
+            }
+            Stmt::Return { value, .. } => match value {
+                Some(value) => out.push_str(&format!("return {};", Self::expr_at(value, depth))),
+                None => out.push_str("return;"),
+            },
+            Stmt::Break { .. } => out.push_str("break;"),
+            Stmt::Continue { .. } => out.push_str("continue;"),
+            Stmt::Throw { value, .. } => {
+                out.push_str(&format!("throw {};", Self::expr_at(value, depth)))
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                out.push_str("try ");
+                Self::write_block(out, body, depth);
+                if let Some(catch) = catch {
+                    out.push_str(&format!(" catch ({}) ", catch.name.lexeme));
+                    Self::write_block(out, &catch.body, depth);
+                }
+                if let Some(finally) = finally {
+                    out.push_str(" finally ");
+                    Self::write_block(out, finally, depth);
+                }
+            }
+        }
+    }
+
+    /// Body of `if`/`while`, always written as a block so that a dangling `else`
+    /// binds the same way when parsed back.
+    fn write_branch(out: &mut String, stmt: &Stmt, depth: usize) {
+        match stmt {
+            Stmt::Block { statements } => Self::write_block(out, statements, depth),
+            stmt => Self::write_block(out, std::slice::from_ref(stmt), depth),
+        }
+    }
+
+    fn write_block(out: &mut String, statements: &[Stmt], depth: usize) {
+        out.push_str("{\n");
+        statements
+            .iter()
+            .for_each(|stmt| Self::write_stmt(out, stmt, depth + 1));
+        out.push_str(&INDENT.repeat(depth));
+        out.push('}');
+    }
+
+    /// Writes `name(params) { body }`, lambdas are written as `fun (params) { body }`.
+    fn write_fun(out: &mut String, fun: &Fun, depth: usize) {
+        let params: Vec<&str> = fun.params.iter().map(|p| p.lexeme.as_str()).collect();
+        match &fun.name {
+            Some(name) => out.push_str(&format!("{}({}) ", name.lexeme, params.join(", "))),
+            None => out.push_str(&format!("fun ({}) ", params.join(", "))),
+        }
+        Self::write_block(out, &fun.body, depth);
+    }
+
+    /// Print `expr` as part of a statement at `depth`.
+    fn expr_at(expr: &Expr, depth: usize) -> String {
+        Self::expr(expr, Precedence::Assignment, depth)
+    }
+
+    /// Print `expr`, wrapped in parentheses if it binds looser than `min`.
+    /// `depth` is the indentation of the enclosing statement, used for lambda bodies.
+    fn expr(expr: &Expr, min: Precedence, depth: usize) -> String {
+        let precedence = Precedence::of(expr);
+        let text = match expr {
+            Expr::Litral { value, .. } => value.to_string(),
+            Expr::Variable { name, .. } => name.lexeme.clone(),
+            Expr::This { .. } => String::from("this"),
+            Expr::Super { method, .. } => format!("super.{}", method.lexeme),
+            Expr::Unary { operator, right } => {
+                let right = Self::expr(right, Precedence::Unary, depth);
+                // Keep `- -a` from turning into a `--a`.
+                if right.starts_with(&operator.lexeme) {
+                    format!("{} {}", operator.lexeme, right)
+                } else {
+                    format!("{}{}", operator.lexeme, right)
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Coalesce {
+                left,
+                operator,
+                right,
+            } => {
+                // `**` is right associative, its right operand may also be a unary.
+                let (left_min, right_min) = if precedence == Precedence::Power {
+                    (Precedence::Postfix, Precedence::Unary)
+                } else {
+                    (precedence, precedence.next())
+                };
+                format!(
+                    "{} {} {}",
+                    Self::expr(left, left_min, depth),
+                    operator.lexeme,
+                    Self::expr(right, right_min, depth)
+                )
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "{} ? {} : {}",
+                Self::expr(condition, Precedence::Coalesce, depth),
+                Self::expr(then_branch, Precedence::Assignment, depth),
+                Self::expr(else_branch, Precedence::Conditional, depth)
+            ),
+            Expr::Grouping { expression, .. } => {
+                format!(
+                    "({})",
+                    Self::expr(expression, Precedence::Assignment, depth)
+                )
+            }
+            Expr::Assign { name, value, .. } => format!(
+                "{} = {}",
+                name.lexeme,
+                Self::expr(value, Precedence::Assignment, depth)
+            ),
+            Expr::CompoundAssign {
+                target,
+                operator,
+                value,
+            } => format!(
+                "{} {} {}",
+                Self::expr(target, Precedence::Call, depth),
+                operator.lexeme,
+                Self::expr(value, Precedence::Assignment, depth)
+            ),
+            Expr::Increment {
+                target,
+                operator,
+                prefix: true,
+            } => format!(
+                "{}{}",
+                operator.lexeme,
+                Self::expr(target, Precedence::Call, depth)
+            ),
+            Expr::Increment {
+                target,
+                operator,
+                prefix: false,
+            } => format!(
+                "{}{}",
+                Self::expr(target, Precedence::Call, depth),
+                operator.lexeme
+            ),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| Self::expr(argument, Precedence::Assignment, depth))
+                    .collect();
+                format!(
+                    "{}({})",
+                    Self::expr(callee, Precedence::Call, depth),
+                    arguments.join(", ")
+                )
+            }
+            Expr::Get { object, name } => {
+                format!(
+                    "{}.{}",
+                    Self::expr(object, Precedence::Call, depth),
+                    name.lexeme
+                )
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => format!(
+                "{}.{} = {}",
+                Self::expr(object, Precedence::Call, depth),
+                name.lexeme,
+                Self::expr(value, Precedence::Assignment, depth)
+            ),
+            Expr::Lambda { fun, .. } => {
+                let mut out = String::new();
+                Self::write_fun(&mut out, fun, depth);
+                out
+            }
+            Expr::Interpolate { parts, .. } => {
+                let mut text = String::from("\"");
+                parts.iter().enumerate().for_each(|(i, part)| match part {
+                    Expr::Litral {
+                        value: LitralValue::STRING(segment),
+                        ..
+                    } if i % 2 == 0 => text.push_str(&escape(segment)),
+                    part => text.push_str(&format!(
+                        "${{{}}}",
+                        Self::expr(part, Precedence::Assignment, depth)
+                    )),
+                });
+                text.push('"');
+                text
+            }
+            Expr::List { elements, .. } => {
+                let elements: Vec<String> = elements
+                    .iter()
+                    .map(|element| Self::expr(element, Precedence::Assignment, depth))
+                    .collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Expr::Map { entries, .. } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            Self::expr(key, Precedence::Assignment, depth),
+                            Self::expr(value, Precedence::Assignment, depth)
+                        )
+                    })
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Expr::Index { object, index, .. } => format!(
+                "{}[{}]",
+                Self::expr(object, Precedence::Call, depth),
+                Self::expr(index, Precedence::Assignment, depth)
+            ),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => format!(
+                "{}[{}] = {}",
+                Self::expr(object, Precedence::Call, depth),
+                Self::expr(index, Precedence::Assignment, depth),
+                Self::expr(value, Precedence::Assignment, depth)
+            ),
+        };
+        if precedence < min {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        crate::compile(String::from(source), None).expect("source should compile")
+    }
+
+    #[test]
+    fn ast_printer_prints_the_book_example() {
+        let stmts = parse("-123 * (45.67);");
+        let Stmt::ExpressionStmt { expression } = &stmts[0] else {
+            panic!("expected an expression statement, got {:?}", stmts[0]);
+        };
+        assert_eq!(
+            AstPrinter::print_expr(expression),
+            "(* (- 123) (group 45.67))"
+        );
+    }
+
+    #[test]
+    fn source_printer_shows_the_for_desugaring() {
+        let stmts = parse("for (var i = 0; i < 3; i = i + 1) print i;");
+        assert_eq!(
+            SourcePrinter::print(&stmts),
+            "{\n    var i = 0;\n    for (; i < 3; i = i + 1) {\n        print i;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn source_printer_output_parses_back_to_the_same_output() {
+        let source = r#"
+            class Shape { init(n) { this.n = n; } area() { return 0; } }
+            class Square < Shape { area() { return super.area() + this.n ** 2; } }
+            fun apply(f, x) { return f(x); }
+            var double = fun (x) { return x * 2; };
+            var xs = [1, 2, [3]];
+            var m = {"a": 1, 2: nil};
+            var s = "n = ${xs[0] + 1} and ${"nested ${m["a"]}"}\t\"quoted\"";
+            for (var i = 0; i < 3; i = i + 1) { if (i == 1) continue; else print i; }
+            while (true) break;
+            var y = -(1 + 2) * 3 // 4 % 5 - 2 ** -1 ** 2;
+            var z = (1 < 2) == !false and nil ?? 3 or false ? 1 : 2 ? 3 : 4;
+            var w = (1 | 2) ^ 3 & ~4 << 1 >> 2;
+            y += 1; xs[0] *= 2; Square(2).n -= 1; y++; --xs[1];
+            try { throw Error("x"); } catch (e) { print e.message; } finally { print "done"; }
+        "#;
+        let printed = SourcePrinter::print(&parse(source));
+        let reprinted = SourcePrinter::print(&parse(&printed));
+        assert_eq!(printed, reprinted);
+        assert_eq!(
+            AstPrinter::print(&parse(&printed)),
+            AstPrinter::print(&parse(&reprinted))
+        );
+    }
+}
@@ -1,18 +1,127 @@
-use crate::interpreter::interpreter_error::InterpreterError;
-use std::error::Error;
+use crate::token::Span;
+use serde::Serialize;
+use std::fmt;
 
-pub fn error(line: usize, message: &str) {
-    eprintln!("[Line {}] Error: {}", line, message)
+pub mod json;
+pub mod renderer;
+
+/// Stage of the pipeline which reported a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Scan,
+    Parse,
+    Resolve,
+    Runtime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
 }
 
-pub fn error_at_compiler(err: &dyn Error) {
-    eprintln!("{}", err);
+/// Extra source location attached to a diagnostic, eg. where a name was first declared.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
 }
 
-pub fn error_at_runtime(err: Box<dyn InterpreterError>) {
-    eprintln!("Runtime error: {}", err);
+/// A problem found in the source, reported by the scanner, parser or resolver.
+/// `Note:` The library never prints diagnostics, that is left to the caller.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub phase: Phase,
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem, eg. `P001`.
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    /// Short text shown under `span` by the renderer.
+    pub label: Option<String>,
+    pub secondary_labels: Vec<Label>,
+    pub notes: Vec<String>,
+    /// Lox call stack of a runtime error, innermost frame first, eg. `at area (shapes.lox:12)`.
+    pub backtrace: Vec<String>,
 }
 
-pub fn report(line: usize, where_in: &str, message: &str) {
-    eprintln!("[Line {}] Error {}: {}", line, where_in, message)
+impl Diagnostic {
+    pub fn error(phase: Phase, code: &'static str, message: &str, span: Span) -> Diagnostic {
+        Diagnostic {
+            phase,
+            severity: Severity::Error,
+            code,
+            message: String::from(message),
+            span,
+            label: None,
+            secondary_labels: Vec::new(),
+            notes: Vec::new(),
+            backtrace: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, label: &str) -> Diagnostic {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn with_secondary_label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.secondary_labels.push(Label {
+            span,
+            message: String::from(message),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(String::from(note));
+        self
+    }
+
+    pub fn with_backtrace(mut self, backtrace: &[String]) -> Diagnostic {
+        self.backtrace = backtrace.to_vec();
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}[{}]: {}",
+            self.span, self.severity, self.code, self.message
+        )?;
+        self.notes
+            .iter()
+            .try_for_each(|note| write!(f, "\n  note: {}", note))?;
+        self.backtrace
+            .iter()
+            .try_for_each(|frame| write!(f, "\n  {}", frame))
+    }
+}
+
+pub type CompileResult<T> = Result<T, Vec<Diagnostic>>;
+
+/// Text of `span` in `source` named `file`, `None` when the span is in another source or
+/// doesn't fit this one.
+fn covered_text<'s>(source: &'s str, file: Option<&str>, span: &Span) -> Option<&'s str> {
+    if span.line == 0 || span.file.as_deref() != file {
+        return None;
+    }
+    source.get(span.start..span.end.max(span.start))
 }
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{covered_text, Diagnostic, Phase, Severity};
+use crate::token::Span;
+
+/// Serializes diagnostics as single line JSON objects, e.g.
+///
+/// ```text
+/// {"phase":"parse","severity":"error","code":"P001","message":"Expect ';' after expression",
+///  "span":{"file":"a.lox","start":8,"end":9,"line":1,"column":9,"end_line":1,"end_column":10},
+///  "lexeme":"}","label":"found '}'","secondary_labels":[],"notes":[],"backtrace":[]}
+/// ```
+/// `end_column` is exclusive, columns are counted in grapheme clusters like `Span::col`.
+/// `end_line`, `end_column` and `lexeme` are null for a span in another file, see
+/// `with_file`.
+pub struct JsonEmitter<'a> {
+    source: &'a str,
+    /// Name of `source`, see `Span::file`.
+    file: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonSpan<'a> {
+    file: Option<&'a str>,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'a> {
+    span: Option<JsonSpan<'a>>,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    phase: Phase,
+    severity: Severity,
+    code: &'a str,
+    message: &'a str,
+    span: Option<JsonSpan<'a>>,
+    lexeme: Option<&'a str>,
+    label: Option<&'a str>,
+    secondary_labels: Vec<JsonLabel<'a>>,
+    notes: &'a [String],
+    backtrace: &'a [String],
+}
+
+impl<'a> JsonEmitter<'a> {
+    /// `source` is the text the diagnostics' spans refer to, see `with_file`.
+    pub fn new(source: &'a str) -> JsonEmitter<'a> {
+        JsonEmitter { source, file: None }
+    }
+
+    /// Name `source`, the text is only looked up for the spans of that file.
+    pub fn with_file(mut self, file: Option<&'a str>) -> JsonEmitter<'a> {
+        self.file = file;
+        self
+    }
+
+    pub fn emit(&self, diagnostic: &Diagnostic) -> String {
+        let span = self.span(&diagnostic.span);
+        let lexeme = covered_text(self.source, self.file, &diagnostic.span);
+        let json = JsonDiagnostic {
+            phase: diagnostic.phase,
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message: &diagnostic.message,
+            span,
+            lexeme,
+            label: diagnostic.label.as_deref(),
+            secondary_labels: diagnostic
+                .secondary_labels
+                .iter()
+                .map(|label| JsonLabel {
+                    span: self.span(&label.span),
+                    message: &label.message,
+                })
+                .collect(),
+            notes: &diagnostic.notes,
+            backtrace: &diagnostic.backtrace,
+        };
+        serde_json::to_string(&json).expect("Diagnostic should serialize to JSON")
+    }
+
+    /// `None` for spans without a location in source.
+    fn span<'d>(&self, span: &'d Span) -> Option<JsonSpan<'d>> {
+        if span.line == 0 {
+            return None;
+        }
+        let covered = covered_text(self.source, self.file, span);
+        let end_line = covered.map(|covered| span.line + covered.matches('\n').count());
+        let end_column = covered.map(|covered| match covered.rfind('\n') {
+            Some(i) => covered[i + 1..].graphemes(true).count() + 1,
+            None => span.col + covered.graphemes(true).count(),
+        });
+        Some(JsonSpan {
+            file: span.file.as_deref(),
+            start: span.start,
+            end: span.end,
+            line: span.line,
+            column: span.col,
+            end_line,
+            end_column,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(file: Option<&str>, start: usize, end: usize) -> Diagnostic {
+        let span = Span {
+            file: file.map(Into::into),
+            start,
+            end,
+            line: 1,
+            col: start + 1,
+        };
+        Diagnostic::error(Phase::Runtime, "E001", "boom", span)
+    }
+
+    #[test]
+    fn span_in_source_has_its_end_and_lexeme() {
+        let json = JsonEmitter::new("print 1 + nil;")
+            .with_file(Some("a.lox"))
+            .emit(&diagnostic(Some("a.lox"), 8, 9));
+        assert!(json.contains(r#""end_line":1,"end_column":10"#), "{}", json);
+        assert!(json.contains(r#""lexeme":"+""#), "{}", json);
+    }
+
+    #[test]
+    fn span_of_another_source_keeps_its_location_only() {
+        let json = JsonEmitter::new("var x = g();")
+            .with_file(Some("<prompt-2>"))
+            .emit(&diagnostic(Some("<prompt-1>"), 4, 5));
+        assert!(json.contains(r#""file":"<prompt-1>""#), "{}", json);
+        assert!(
+            json.contains(r#""end_line":null,"end_column":null"#),
+            "{}",
+            json
+        );
+        assert!(json.contains(r#""lexeme":null"#), "{}", json);
+    }
+
+    #[test]
+    fn span_inside_a_character_has_no_lexeme() {
+        let json = JsonEmitter::new("var éé = 1;").emit(&diagnostic(None, 5, 6));
+        assert!(json.contains(r#""lexeme":null"#), "{}", json);
+    }
+}
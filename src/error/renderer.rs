@@ -0,0 +1,221 @@
+use std::fmt::Write;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{covered_text, Diagnostic, Severity};
+use crate::token::Span;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders diagnostics with the offending source lines, e.g.
+///
+/// ```text
+/// error[R008]: Already a variable with this name in this scope
+///  --> shapes.lox:2:18
+///   |
+/// 2 | { var b = 2; var b = 3; }
+///   |       - variable first declared here
+///   |                  ^ redeclared here
+/// ```
+pub struct Renderer<'a> {
+    source: &'a str,
+    /// Name of `source`, see `Span::file`.
+    file: Option<&'a str>,
+    color: bool,
+}
+
+/// A single underline, `primary` ones use '^' and the rest '-'.
+struct Marker<'d> {
+    span: &'d Span,
+    message: Option<&'d str>,
+    primary: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// `source` is the text the diagnostics' spans refer to, see `with_file`.
+    pub fn new(source: &'a str, color: bool) -> Renderer<'a> {
+        Renderer {
+            source,
+            file: None,
+            color,
+        }
+    }
+
+    /// Name `source`, only the spans of that file are shown with their source line.
+    pub fn with_file(mut self, file: Option<&'a str>) -> Renderer<'a> {
+        self.file = file;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let (severity, severity_color) = match diagnostic.severity {
+            Severity::Error => ("error", RED),
+            Severity::Warning => ("warning", YELLOW),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(
+                severity_color,
+                &format!("{}[{}]", severity, diagnostic.code)
+            ),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+
+        let mut markers = vec![Marker {
+            span: &diagnostic.span,
+            message: diagnostic.label.as_deref(),
+            primary: true,
+        }];
+        markers.extend(diagnostic.secondary_labels.iter().map(|label| Marker {
+            span: &label.span,
+            message: Some(label.message.as_str()),
+            primary: false,
+        }));
+        // Spans without a location in this source can't be shown.
+        markers.retain(|marker| covered_text(self.source, self.file, marker.span).is_some());
+
+        let mut lines: Vec<usize> = markers.iter().map(|marker| marker.span.line).collect();
+        lines.sort_unstable();
+        lines.dedup();
+        let gutter = " ".repeat(lines.last().map_or(1, |line| line.to_string().len()));
+
+        if diagnostic.span.line > 0 {
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                gutter,
+                self.paint(BLUE, "-->"),
+                diagnostic.span
+            );
+        }
+        if !lines.is_empty() {
+            let _ = writeln!(out, "{} {}", gutter, self.paint(BLUE, "|"));
+        }
+        for line in lines {
+            let mut line_markers: Vec<&Marker> = markers
+                .iter()
+                .filter(|marker| marker.span.line == line)
+                .collect();
+            line_markers.sort_by_key(|marker| marker.span.start);
+            let (line_start, text) = self.line_at(line_markers[0].span.start);
+            let _ = writeln!(
+                out,
+                "{} {}",
+                self.paint(BLUE, &format!("{:>width$} |", line, width = gutter.len())),
+                text
+            );
+            for marker in line_markers {
+                let _ = writeln!(
+                    out,
+                    "{} {} {}",
+                    gutter,
+                    self.paint(BLUE, "|"),
+                    self.underline(marker, line_start, text, severity_color)
+                );
+            }
+        }
+
+        diagnostic.notes.iter().for_each(|note| {
+            let _ = writeln!(out, "{} {} note: {}", gutter, self.paint(BLUE, "="), note);
+        });
+        if !diagnostic.backtrace.is_empty() {
+            let _ = writeln!(out, "{} {} backtrace:", gutter, self.paint(BLUE, "="));
+            diagnostic.backtrace.iter().for_each(|frame| {
+                let _ = writeln!(out, "{}     {}", gutter, frame);
+            });
+        }
+        out
+    }
+
+    /// Byte offset where the line containing `offset` starts and its text.
+    fn line_at(&self, offset: usize) -> (usize, &'a str) {
+        let start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.source[start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| start + i);
+        (start, self.source[start..end].trim_end_matches('\r'))
+    }
+
+    fn underline(&self, marker: &Marker, line_start: usize, text: &str, color: &str) -> String {
+        let before = &text[..(marker.span.start - line_start).min(text.len())];
+        // Keep tabs so that the marker lines up with the source line.
+        let padding: String = before
+            .graphemes(true)
+            .map(|g| if g == "\t" { '\t' } else { ' ' })
+            .collect();
+        let end = (marker.span.end.max(marker.span.start) - line_start).min(text.len());
+        let width = text[before.len()..end].graphemes(true).count().max(1);
+
+        let (symbol, color) = if marker.primary {
+            ("^", color)
+        } else {
+            ("-", BLUE)
+        };
+        let mut underline = symbol.repeat(width);
+        if let Some(message) = marker.message {
+            underline = format!("{} {}", underline, message);
+        }
+        format!("{}{}", padding, self.paint(color, &underline))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            String::from(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Phase;
+
+    fn diagnostic(file: Option<&str>, start: usize, end: usize) -> Diagnostic {
+        let span = Span {
+            file: file.map(Into::into),
+            start,
+            end,
+            line: 1,
+            col: start + 1,
+        };
+        Diagnostic::error(Phase::Runtime, "E001", "boom", span)
+    }
+
+    #[test]
+    fn span_in_source_is_underlined() {
+        let rendered = Renderer::new("print 1 + nil;", false)
+            .with_file(Some("a.lox"))
+            .render(&diagnostic(Some("a.lox"), 8, 9));
+        assert!(rendered.contains("1 | print 1 + nil;"), "{}", rendered);
+        assert!(rendered.contains("|         ^"), "{}", rendered);
+    }
+
+    #[test]
+    fn span_of_another_source_has_no_snippet() {
+        let rendered = Renderer::new("var x = g();", false)
+            .with_file(Some("<prompt-2>"))
+            .render(&diagnostic(Some("<prompt-1>"), 4, 5));
+        assert!(rendered.contains("--> <prompt-1>:1:5"), "{}", rendered);
+        assert!(!rendered.contains("var x"), "{}", rendered);
+    }
+
+    #[test]
+    fn span_inside_a_character_has_no_snippet() {
+        let rendered = Renderer::new("var éé = 1;", false).render(&diagnostic(None, 5, 6));
+        assert!(!rendered.contains("var éé"), "{}", rendered);
+    }
+
+    #[test]
+    fn span_past_the_end_has_no_snippet() {
+        let rendered = Renderer::new("nil;", false).render(&diagnostic(None, 10, 12));
+        assert!(!rendered.contains("nil;"), "{}", rendered);
+    }
+}
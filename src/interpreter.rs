@@ -1,54 +1,159 @@
 use crate::{
     ast::{Expr, Stmt},
-    token::{Token, TokenType},
+    token::{Span, Token, TokenType},
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Instant};
 
 pub mod environment;
 pub mod interpreter_error;
+pub mod limits;
 pub mod lox_class;
 pub mod lox_function;
+pub mod memory;
 pub mod native_functions;
 pub mod runtime_value;
 
 use self::{
     environment::Environment,
     interpreter_error::{
-        EarlyReturn, EarlyReturnReason, InterpreterError, RuntimeError, RuntimeResult,
+        EarlyReturn, EarlyReturnReason, Exception, InterpreterError, Interrupted, RuntimeError,
+        RuntimeResult,
     },
+    limits::{CancelHandle, Limits},
     lox_class::{ClassInstance, LoxClass},
     lox_function::LoxFunction,
-    native_functions::NativeFnClock,
-    runtime_value::{LoxCallable, RuntimeValue},
+    memory::{Allocation, Charged, MemoryMeter},
+    native_functions::{NativeFnClock, NativeFnLen, NativeFnPush},
+    runtime_value::{LoxCallable, LoxCallableType, LoxInstance, LoxString, MapKey, RuntimeValue},
 };
 
+/// Lox source of the globals defined before any script, see `load_prelude`.
+const PRELUDE: &str = include_str!("interpreter/prelude.lox");
+
+/// See `Interpreter::with_max_call_depth`.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// Host stack left when a statement or an expression starts, below it the interpreter
+/// continues on a new segment so that deep Lox recursion can't overflow the host thread.
+const STACK_RED_ZONE: usize = 128 * 1024;
+/// Size of the segments the host stack is extended with.
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+/// A backtrace line repeated more times than this, eg. by recursion, is cut short.
+const BACKTRACE_REPEATS: usize = 3;
+
 pub struct Interpreter {
     globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    /// The prelude's `Error` class, kept aside in case a script shadows the global.
+    error_class: Option<Rc<dyn LoxCallable>>,
+    /// Functions being called, innermost last.
+    call_stack: Vec<CallFrame>,
+    max_call_depth: usize,
+    limits: Limits,
+    memory: MemoryMeter,
+}
+
+struct CallFrame {
+    /// See `LoxCallable::name`.
+    function: String,
+    /// Where the function was called from.
+    call_site: Span,
+    native: bool,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
         let globals = Interpreter::define_globals();
         let globals_clone = Rc::clone(&globals);
-        Interpreter {
+        let mut interpreter = Interpreter {
             globals,
             environment: globals_clone,
-        }
+            error_class: None,
+            call_stack: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            limits: Limits::default(),
+            memory: MemoryMeter::default(),
+        };
+        interpreter.load_prelude();
+        interpreter
     }
 
-    pub fn interpret(&mut self, statements: &Vec<Stmt>) -> RuntimeResult<()> {
+    /// A call nested deeper than `max_call_depth` raises a "Stack overflow." runtime error.
+    /// The host stack is extended on the heap as needed, so any thread can run the
+    /// interpreter whatever the depth.
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Interpreter {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Stop running after `max_steps` steps, a step being a statement executed or an
+    /// expression evaluated. The budget is shared by every script run by this interpreter.
+    pub fn with_step_budget(mut self, max_steps: u64) -> Interpreter {
+        self.limits.max_steps = Some(max_steps);
+        self.limits.steps = 0;
+        self
+    }
+
+    /// Stop running once `deadline` has passed.
+    /// `Note:` The clock is only read every few steps, a native call isn't interrupted.
+    pub fn with_deadline(mut self, deadline: Instant) -> Interpreter {
+        self.limits.deadline = Some(deadline);
+        self
+    }
+
+    /// Handle to stop this interpreter from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.limits.cancel_handle()
+    }
+
+    /// Stop running when the values of the scripts would take more than about `max_bytes`,
+    /// see `MemoryMeter` for what is counted.
+    pub fn with_memory_limit(self, max_bytes: usize) -> Interpreter {
+        self.memory.set_limit(max_bytes);
+        self
+    }
+
+    /// Approximate bytes taken by the values alive, the prelude's included.
+    pub fn memory_used(&self) -> usize {
+        self.memory.used()
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> RuntimeResult<()> {
         statements
             .iter()
             .try_for_each(|statement| self.execute(statement))
     }
 
+    fn load_prelude(&mut self) {
+        let stmts = crate::compile(String::from(PRELUDE), Some("<prelude>"))
+            .expect("prelude should compile");
+        self.interpret(&stmts).expect("prelude should run");
+        if let Ok(RuntimeValue::Callable(error_class)) = self.globals.borrow().get_at("Error", 0) {
+            self.error_class = Some(error_class);
+        }
+    }
+
     fn define_globals() -> Rc<RefCell<Environment>> {
         let environment = Rc::new(RefCell::new(Environment::new()));
         let clock = Rc::new(NativeFnClock {});
         (*environment)
             .borrow_mut()
             .define("clock", RuntimeValue::Callable(clock));
+        let len = Rc::new(NativeFnLen {});
+        (*environment)
+            .borrow_mut()
+            .define("len", RuntimeValue::Callable(len));
+        let push = Rc::new(NativeFnPush {});
+        (*environment)
+            .borrow_mut()
+            .define("push", RuntimeValue::Callable(push));
         environment
     }
 
@@ -58,6 +163,15 @@ impl Interpreter {
 
     /// Execute statement
     fn execute(&mut self, statement: &Stmt) -> RuntimeResult<()> {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            self.execute_statement(statement)
+        })
+    }
+
+    fn execute_statement(&mut self, statement: &Stmt) -> RuntimeResult<()> {
+        if let Err(reason) = self.limits.tick() {
+            return Err(Interrupted::new(reason, Some(statement.span())));
+        }
         match statement {
             Stmt::Class {
                 name,
@@ -84,33 +198,43 @@ impl Interpreter {
                     .define(&name.lexeme, RuntimeValue::Nil);
 
                 // 'super' environment
-                super_class.as_ref().map(|super_class| {
+                if let Some(super_class) = super_class.as_ref() {
                     let mut environment = Environment::new_with(Rc::clone(&self.environment));
                     environment.define(
                         "super",
                         RuntimeValue::Callable(Rc::clone(super_class) as Rc<dyn LoxCallable>),
                     );
                     self.environment = Rc::new(RefCell::new(environment));
-                });
+                }
 
                 let mut methods_map: HashMap<String, Rc<LoxFunction>> = HashMap::new();
-                methods.iter().for_each(|method| {
-                    methods_map.insert(
-                        method.name.lexeme.clone(),
-                        Rc::new(LoxFunction::new(
-                            method,
-                            &self.environment,
-                            method.name.lexeme.eq("init"),
-                        )),
-                    );
-                });
+                for method in methods {
+                    if let Some(method_name) = &method.name {
+                        let allocation =
+                            self.allocate(size_of::<LoxFunction>(), Some(&method_name.span))?;
+                        methods_map.insert(
+                            method_name.lexeme.clone(),
+                            Rc::new(
+                                LoxFunction::new(
+                                    method,
+                                    &self.environment,
+                                    method_name.lexeme.eq("init"),
+                                )
+                                .in_class(&name.lexeme)
+                                .with_allocation(allocation),
+                            ),
+                        );
+                    }
+                }
 
                 // Pop/discard 'super' environment
-                super_class.as_ref().map(|_| {
+                if super_class.is_some() {
                     let enclosing_environment =
                         self.environment.as_ref().borrow_mut().take_enclosing();
-                    enclosing_environment.map(|env| self.environment = env);
-                });
+                    if let Some(env) = enclosing_environment {
+                        self.environment = env;
+                    }
+                }
 
                 let kclass = Rc::new(LoxClass::new(&name.lexeme, super_class, methods_map));
 
@@ -139,9 +263,22 @@ impl Interpreter {
                     self.execute(else_branch)?;
                 }
             }
-            Stmt::WhileStmt { condition, body } => {
+            Stmt::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => {
                 while bool::from(self.evaluate(condition)?) {
-                    self.execute(body)?;
+                    if let Err(err) = self.execute(body) {
+                        match err.early_return_reason() {
+                            Some(EarlyReturnReason::BreakLoop) => break,
+                            Some(EarlyReturnReason::ContinueLoop) => (),
+                            _ => return Err(err),
+                        }
+                    }
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
                 }
             }
             Stmt::PrintStmt { expression } => {
@@ -164,29 +301,106 @@ impl Interpreter {
                     ));
                 }
             }
+            Stmt::Break { keyword } => {
+                return Err(EarlyReturn::new(keyword, EarlyReturnReason::BreakLoop));
+            }
+            Stmt::Continue { keyword } => {
+                return Err(EarlyReturn::new(keyword, EarlyReturnReason::ContinueLoop));
+            }
+            Stmt::Throw { keyword, value } => {
+                let value = self.evaluate(value)?;
+                return Err(Exception::new(keyword, value));
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                let existing_environment = Rc::clone(&self.environment);
+                let mut result =
+                    self.execute_block(body, Environment::new_with(existing_environment));
+                if let (Err(err), Some(catch)) = (&result, catch) {
+                    // `return`, `break` and `continue` go through the try untouched, and so
+                    // does a run stopped by the host.
+                    if !err.is_early_return() && err.interruption().is_none() {
+                        let value = match err.thrown_value() {
+                            Some(value) => Ok(value),
+                            None => self.error_instance(err.as_ref()),
+                        };
+                        result = value.and_then(|value| {
+                            let mut environment =
+                                Environment::new_with(Rc::clone(&self.environment));
+                            environment.define(&catch.name.lexeme, value);
+                            self.execute_block(&catch.body, environment)
+                        });
+                    }
+                }
+                if let Some(finally) = finally {
+                    // Leaving the finally block early replaces the outcome of the try.
+                    let existing_environment = Rc::clone(&self.environment);
+                    self.execute_block(finally, Environment::new_with(existing_environment))?;
+                }
+                result?;
+            }
             Stmt::Block { statements } => {
                 let existing_environment = Rc::clone(&self.environment);
                 self.execute_block(statements, Environment::new_with(existing_environment))?;
             }
             Stmt::Function(fun) => {
-                let function = Rc::new(LoxFunction::new(fun, &self.environment, false));
-                self.environment
-                    .borrow_mut()
-                    .define(fun.name.lexeme.as_str(), RuntimeValue::Callable(function))
+                let allocation = self.allocate(
+                    size_of::<LoxFunction>(),
+                    fun.name.as_ref().map(|name| &name.span),
+                )?;
+                let function = Rc::new(
+                    LoxFunction::new(fun, &self.environment, false).with_allocation(allocation),
+                );
+                if let Some(name) = &fun.name {
+                    self.environment
+                        .borrow_mut()
+                        .define(name.lexeme.as_str(), RuntimeValue::Callable(function))
+                }
             }
         }
         Ok(())
     }
 
+    /// The `Error` instance a `catch` clause binds for a runtime error, carrying its
+    /// message and line.
+    fn error_instance(&mut self, err: &dyn InterpreterError) -> RuntimeResult {
+        let error_class = Rc::clone(
+            self.error_class
+                .as_ref()
+                .expect("prelude should define Error"),
+        );
+        let message = err.message().map_or_else(|| err.to_string(), String::from);
+        let message = RuntimeValue::String(LoxString::from(message));
+        let instance = error_class.call(self, vec![message])?;
+        if let (RuntimeValue::Instance(error), Some(token)) = (&instance, err.token()) {
+            let line = Token::new(TokenType::IDENTIFIER, String::from("line"), Span::default());
+            error.set(&line, RuntimeValue::Number(token.span.line as f64))?;
+        }
+        Ok(instance)
+    }
+
     /// Helper for executing block
     fn execute_block(
         &mut self,
-        statements: &Vec<Stmt>,
+        statements: &[Stmt],
         block_environment: Environment,
     ) -> RuntimeResult<()> {
+        let allocation = self
+            .memory
+            .allocate(size_of::<Environment>())
+            .map_err(|reason| {
+                // The span is only worked out when it gets reported.
+                Interrupted::new(reason, statements.first().map(Stmt::span))
+                    as Box<dyn InterpreterError>
+            })?;
+
         // set block environment
         let old_environment = Rc::clone(&self.environment);
-        self.environment = Rc::new(RefCell::new(block_environment));
+        self.environment = Rc::new(RefCell::new(block_environment.with_allocation(allocation)));
 
         let result = statements
             .iter()
@@ -200,19 +414,33 @@ impl Interpreter {
 
     /// Helper for evaluating expression
     fn evaluate(&mut self, expr: &Expr) -> RuntimeResult {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            self.evaluate_expr(expr)
+        })
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> RuntimeResult {
+        if let Err(reason) = self.limits.tick() {
+            return Err(Interrupted::new(reason, Some(expr.span())));
+        }
         match expr {
-            Expr::Grouping { expression } => self.evaluate(expression),
+            Expr::Grouping { expression, .. } => self.evaluate(expression),
             Expr::Unary { operator, right } => {
                 let right = self.evaluate(right)?;
 
-                match operator.token_type {
+                let result = match operator.token_type {
                     TokenType::MINUS => -right,
                     TokenType::BANG => !right.is_truthy(),
+                    TokenType::TILDE => right.bit_not(),
                     _ => Err(RuntimeError::new(
                         operator,
-                        "Only - and ! are supported as a unary operator!",
-                    )),
-                }
+                        "Only -, ! and ~ are supported as a unary operator!",
+                    ) as Box<dyn InterpreterError>),
+                };
+                result.map_err(|err| {
+                    RuntimeError::new(operator, err.message().unwrap_or_default())
+                        as Box<dyn InterpreterError>
+                })
             }
             Expr::Binary {
                 left,
@@ -223,9 +451,17 @@ impl Interpreter {
                 let right = self.evaluate(right)?;
                 let result = match operator.token_type {
                     TokenType::MINUS => left - right,
-                    TokenType::PLUS => left + right,
+                    TokenType::PLUS => return self.add(operator, left, right),
                     TokenType::STAR => left * right,
                     TokenType::SLASH => left / right,
+                    TokenType::SLASH_SLASH => left.floor_div(right),
+                    TokenType::PERCENT => left % right,
+                    TokenType::STAR_STAR => left.pow(right),
+                    TokenType::AMPERSAND => left & right,
+                    TokenType::PIPE => left | right,
+                    TokenType::CARET => left ^ right,
+                    TokenType::LESS_LESS => left << right,
+                    TokenType::GREATER_GREATER => left >> right,
                     TokenType::GREATER => Ok(RuntimeValue::Boolean(left > right)),
                     TokenType::GREATER_EQUAL => Ok(RuntimeValue::Boolean(left >= right)),
                     TokenType::LESS => Ok(RuntimeValue::Boolean(left < right)),
@@ -256,7 +492,22 @@ impl Interpreter {
                     }
                 }
             }
-            Expr::Litral(litral) => Ok(litral.clone().into()),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if bool::from(&self.evaluate(condition)?) {
+                    self.evaluate(then_branch)
+                } else {
+                    self.evaluate(else_branch)
+                }
+            }
+            Expr::Coalesce { left, right, .. } => match self.evaluate(left)? {
+                RuntimeValue::Nil => self.evaluate(right),
+                left => Ok(left),
+            },
+            Expr::Litral { value, .. } => Ok(value.clone().into()),
             Expr::Variable { name, depth } => match depth {
                 Some(depth) => self.environment.borrow().get_at(&name.lexeme, *depth),
                 None => self.globals.borrow().get(name),
@@ -274,30 +525,105 @@ impl Interpreter {
             },
             Expr::Assign { name, value, depth } => {
                 let value = self.evaluate(value)?;
-                match depth {
-                    Some(depth) => {
-                        self.environment
-                            .borrow_mut()
-                            .assign_at(&name.lexeme, value, *depth)
-                    }
-                    None => self.globals.borrow_mut().assign(name, value),
-                }
+                self.assign_variable(name, depth, value)
+            }
+            Expr::CompoundAssign {
+                target,
+                operator,
+                value,
+            } => {
+                let place = self.evaluate_place(target)?;
+                let current = self.read_place(&place)?;
+                let value = self.evaluate(value)?;
+                let value = self.compound_operation(operator, current, value)?;
+                self.write_place(&place, value)
+            }
+            Expr::Increment {
+                target,
+                operator,
+                prefix,
+            } => {
+                let place = self.evaluate_place(target)?;
+                let current = self.read_place(&place)?;
+                let value =
+                    self.compound_operation(operator, current.clone(), RuntimeValue::Number(1.0))?;
+                let value = self.write_place(&place, value)?;
+                Ok(if *prefix { value } else { current })
             }
             Expr::Call {
                 callee,
                 paran,
                 arguments,
             } => self.evaluate_function_call(callee, paran, arguments),
+            Expr::Lambda { fun, span, .. } => {
+                let allocation = self.allocate(size_of::<LoxFunction>(), Some(span))?;
+                Ok(RuntimeValue::Callable(Rc::new(
+                    LoxFunction::new(fun, &self.environment, false).with_allocation(allocation),
+                )))
+            }
+            Expr::Interpolate { parts, span } => {
+                let mut value = String::new();
+                for part in parts {
+                    value.push_str(&self.evaluate(part)?.to_string());
+                }
+                let allocation = self.allocate(value.len(), Some(span))?;
+                Ok(RuntimeValue::String(LoxString::charged(value, allocation)))
+            }
+            Expr::List { elements, span } => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                let bytes =
+                    size_of::<Vec<RuntimeValue>>() + values.len() * size_of::<RuntimeValue>();
+                let allocation = self.allocate(bytes, Some(span))?;
+                Ok(RuntimeValue::List(Rc::new(Charged::new(
+                    RefCell::new(values),
+                    allocation,
+                ))))
+            }
+            Expr::Map {
+                entries,
+                brace,
+                span,
+            } => {
+                let mut map = HashMap::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let key = Self::map_key(brace, &self.evaluate(key)?)?;
+                    let value = self.evaluate(value)?;
+                    map.insert(key, value);
+                }
+                let bytes = size_of::<HashMap<MapKey, RuntimeValue>>()
+                    + map.keys().map(Self::map_entry_size).sum::<usize>();
+                let allocation = self.allocate(bytes, Some(span))?;
+                Ok(RuntimeValue::Map(Rc::new(Charged::new(
+                    RefCell::new(map),
+                    allocation,
+                ))))
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                Self::get_index(bracket, &object, &index)
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+                Self::set_index(bracket, &object, &index, value)
+            }
             Expr::Get { object, name } => {
                 if let RuntimeValue::Instance(instance) = self.evaluate(object)? {
-                    let value = instance.get(name);
-                    match value {
-                        Some(value) => Ok(value),
-                        None => Err(RuntimeError::new(
-                            name,
-                            format!("Property {} not found in the object", name.lexeme).as_str(),
-                        )),
-                    }
+                    Self::get_field(name, &instance)
                 } else {
                     Err(RuntimeError::new(name, "Only instance can have properties"))
                 }
@@ -310,7 +636,7 @@ impl Interpreter {
                 let object = self.evaluate(object)?;
                 if let RuntimeValue::Instance(instance) = object {
                     let value = self.evaluate(value)?;
-                    Ok(instance.set(name, value))
+                    instance.set(name, value)
                 } else {
                     Err(RuntimeError::new(
                         name,
@@ -321,6 +647,216 @@ impl Interpreter {
         }
     }
 
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        depth: &Option<usize>,
+        value: RuntimeValue,
+    ) -> RuntimeResult {
+        match depth {
+            Some(depth) => self
+                .environment
+                .borrow_mut()
+                .assign_at(&name.lexeme, value, *depth),
+            None => self.globals.borrow_mut().assign(name, value),
+        }
+    }
+
+    fn get_field(name: &Token, instance: &Rc<dyn LoxInstance>) -> RuntimeResult {
+        match instance.get(name) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::new(
+                name,
+                format!("Property {} not found in the object", name.lexeme).as_str(),
+            )),
+        }
+    }
+
+    fn get_index(bracket: &Token, object: &RuntimeValue, index: &RuntimeValue) -> RuntimeResult {
+        match object {
+            RuntimeValue::List(list) => {
+                let list = list.borrow();
+                let index = Self::list_index(bracket, index, list.len())?;
+                Ok(list[index].clone())
+            }
+            // A missing key reads as nil.
+            RuntimeValue::Map(map) => Ok(map
+                .borrow()
+                .get(&Self::map_key(bracket, index)?)
+                .cloned()
+                .unwrap_or(RuntimeValue::Nil)),
+            _ => Err(RuntimeError::new(
+                bracket,
+                "Only lists and maps can be indexed",
+            )),
+        }
+    }
+
+    fn set_index(
+        bracket: &Token,
+        object: &RuntimeValue,
+        index: &RuntimeValue,
+        value: RuntimeValue,
+    ) -> RuntimeResult {
+        match object {
+            RuntimeValue::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = Self::list_index(bracket, index, list.len())?;
+                list[index] = value.clone();
+                Ok(value)
+            }
+            RuntimeValue::Map(map) => {
+                let key = Self::map_key(bracket, index)?;
+                if !map.borrow().contains_key(&key) {
+                    if let Err(reason) = map.allocation().grow(Self::map_entry_size(&key)) {
+                        return Err(Interrupted::new(reason, Some(bracket.span.clone())));
+                    }
+                }
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            _ => Err(RuntimeError::new(
+                bracket,
+                "Only lists and maps can be indexed",
+            )),
+        }
+    }
+
+    /// Evaluate the object and index of the target of a compound assignment or an
+    /// increment, so that they aren't evaluated again when it is written.
+    fn evaluate_place<'a>(&mut self, target: &'a Expr) -> RuntimeResult<Place<'a>> {
+        match target {
+            Expr::Variable { name, depth } => Ok(Place::Variable {
+                name,
+                depth: *depth,
+            }),
+            Expr::Get { object, name } => match self.evaluate(object)? {
+                RuntimeValue::Instance(instance) => Ok(Place::Field { instance, name }),
+                _ => Err(RuntimeError::new(name, "Only instance can have properties")),
+            },
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => Ok(Place::Element {
+                object: self.evaluate(object)?,
+                bracket,
+                index: self.evaluate(index)?,
+            }),
+            _ => panic!("Only variables, fields and indices can be assigned to"),
+        }
+    }
+
+    fn read_place(&self, place: &Place) -> RuntimeResult {
+        match place {
+            Place::Variable {
+                name,
+                depth: Some(depth),
+            } => self.environment.borrow().get_at(&name.lexeme, *depth),
+            Place::Variable { name, depth: None } => self.globals.borrow().get(name),
+            Place::Field { instance, name } => Self::get_field(name, instance),
+            Place::Element {
+                object,
+                bracket,
+                index,
+            } => Self::get_index(bracket, object, index),
+        }
+    }
+
+    fn write_place(&mut self, place: &Place, value: RuntimeValue) -> RuntimeResult {
+        match place {
+            Place::Variable { name, depth } => self.assign_variable(name, depth, value),
+            Place::Field { instance, name } => instance.set(name, value),
+            Place::Element {
+                object,
+                bracket,
+                index,
+            } => Self::set_index(bracket, object, index, value),
+        }
+    }
+
+    /// `left + right`, a concatenated string is charged before it is built.
+    fn add(&self, operator: &Token, left: RuntimeValue, right: RuntimeValue) -> RuntimeResult {
+        if let (RuntimeValue::String(lhs), RuntimeValue::String(rhs)) = (&left, &right) {
+            let allocation = self.allocate(lhs.len() + rhs.len(), Some(&operator.span))?;
+            let text = format!("{}{}", lhs, rhs);
+            return Ok(RuntimeValue::String(LoxString::charged(text, allocation)));
+        }
+        (left + right).map_err(|err| {
+            RuntimeError::new(operator, err.message().unwrap_or_default())
+                as Box<dyn InterpreterError>
+        })
+    }
+
+    /// Charge `bytes` to the memory meter, a failure is reported at `span`.
+    fn allocate(&self, bytes: usize, span: Option<&Span>) -> RuntimeResult<Allocation> {
+        self.memory
+            .allocate(bytes)
+            .map_err(|reason| Interrupted::new(reason, span.cloned()) as Box<dyn InterpreterError>)
+    }
+
+    /// Arithmetic behind a compound assignment (`+=`) or an increment (`++`).
+    fn compound_operation(
+        &self,
+        operator: &Token,
+        current: RuntimeValue,
+        value: RuntimeValue,
+    ) -> RuntimeResult {
+        let result = match operator.token_type {
+            TokenType::PLUS_EQUAL | TokenType::PLUS_PLUS => {
+                return self.add(operator, current, value)
+            }
+            TokenType::MINUS_EQUAL | TokenType::MINUS_MINUS => current - value,
+            TokenType::STAR_EQUAL => current * value,
+            TokenType::SLASH_EQUAL => current / value,
+            TokenType::PERCENT_EQUAL => current % value,
+            _ => Err(
+                RuntimeError::new(operator, "Unsupported compound assignment")
+                    as Box<dyn InterpreterError>,
+            ),
+        };
+        result.map_err(|err| {
+            RuntimeError::new(operator, err.message().unwrap_or_default())
+                as Box<dyn InterpreterError>
+        })
+    }
+
+    /// Position in a list of `len` elements, `index` must be a whole number in range.
+    fn list_index(bracket: &Token, index: &RuntimeValue, len: usize) -> RuntimeResult<usize> {
+        match index {
+            RuntimeValue::Number(index) if index.fract() != 0.0 => Err(RuntimeError::new(
+                bracket,
+                format!("List index {} is not an integer", index).as_str(),
+            )),
+            RuntimeValue::Number(index) if *index < 0.0 || *index >= len as f64 => {
+                Err(RuntimeError::new(
+                    bracket,
+                    format!(
+                        "List index {} out of range for a list of length {}",
+                        index, len
+                    )
+                    .as_str(),
+                ))
+            }
+            RuntimeValue::Number(index) => Ok(*index as usize),
+            _ => Err(RuntimeError::new(bracket, "List index must be a number")),
+        }
+    }
+
+    fn map_key(token: &Token, key: &RuntimeValue) -> RuntimeResult<MapKey> {
+        MapKey::try_from(key)
+            .map_err(|message| RuntimeError::new(token, message) as Box<dyn InterpreterError>)
+    }
+
+    /// Memory charged for a map entry with `key`.
+    fn map_entry_size(key: &MapKey) -> usize {
+        let text = match key {
+            MapKey::String(text) => text.len(),
+            _ => 0,
+        };
+        size_of::<(MapKey, RuntimeValue)>() + text
+    }
+
     fn evaluate_super(
         &mut self,
         keyword: &Token,
@@ -369,12 +905,52 @@ impl Interpreter {
         }
     }
 
+    /// One line per function being called, innermost first, eg. `at area (shapes.lox:12)`.
+    /// Each function is located at the line it was executing, `err`'s line for the
+    /// innermost one and the call to the next function for the others.
+    fn backtrace(&self, err: &dyn InterpreterError) -> Vec<String> {
+        let mut location = err.span().unwrap_or_default();
+        let mut backtrace = Vec::with_capacity(self.call_stack.len() + 1);
+        for frame in self.call_stack.iter().rev() {
+            if frame.native {
+                backtrace.push(format!("at {} (native)", frame.function));
+            } else {
+                backtrace.push(Self::frame_line(&frame.function, &location));
+            }
+            location = frame.call_site.clone();
+        }
+        backtrace.push(Self::frame_line("<script>", &location));
+
+        backtrace
+            .chunk_by(|line, next| line == next)
+            .flat_map(|run| {
+                let mut lines = run[..run.len().min(BACKTRACE_REPEATS)].to_vec();
+                if run.len() > BACKTRACE_REPEATS {
+                    lines.push(format!(
+                        "[previous line repeated {} more times]",
+                        run.len() - BACKTRACE_REPEATS
+                    ));
+                }
+                lines
+            })
+            .collect()
+    }
+
+    /// `at name (file:line)`, the file is left out for prompt input.
+    fn frame_line(function: &str, location: &Span) -> String {
+        match (&location.file, location.line) {
+            (_, 0) => format!("at {} (line unknown)", function),
+            (Some(file), line) => format!("at {} ({}:{})", function, file, line),
+            (None, line) => format!("at {} (line {})", function, line),
+        }
+    }
+
     /// Helper for evaluating function call
     fn evaluate_function_call(
         &mut self,
-        callee: &Box<Expr>,
+        callee: &Expr,
         paran: &Token,
-        arguments: &Vec<Expr>,
+        arguments: &[Expr],
     ) -> RuntimeResult {
         if let RuntimeValue::Callable(function) = self.evaluate(callee)? {
             let mut argument_vals = Vec::new();
@@ -399,7 +975,30 @@ impl Interpreter {
                         Err(err) => Err(err),
                     })?;
 
-                function.call(self, argument_vals)
+                if self.call_stack.len() >= self.max_call_depth {
+                    return Err(RuntimeError::new(paran, "Stack overflow."));
+                }
+                let native = function.callable_type() == LoxCallableType::NativeFunction;
+                self.call_stack.push(CallFrame {
+                    function: function.name(),
+                    call_site: paran.span.clone(),
+                    native,
+                });
+                let result = function.call(self, argument_vals).map_err(|mut err| {
+                    if let (Some(reason), None) = (err.interruption(), err.span()) {
+                        // Native functions and classes don't know where they were called from.
+                        err = Interrupted::new(reason, Some(paran.span.clone()));
+                    } else if native && err.token().is_none() {
+                        err = RuntimeError::new(paran, err.message().unwrap_or_default());
+                    }
+                    // Only the innermost call records the stack.
+                    if !err.is_early_return() && err.backtrace().is_empty() {
+                        err.set_backtrace(self.backtrace(err.as_ref()));
+                    }
+                    err
+                });
+                self.call_stack.pop();
+                result
             }
         } else {
             Err(RuntimeError::new(
@@ -409,3 +1008,20 @@ impl Interpreter {
         }
     }
 }
+
+/// Target of a compound assignment or an increment, with its object and index evaluated.
+enum Place<'a> {
+    Variable {
+        name: &'a Token,
+        depth: Option<usize>,
+    },
+    Field {
+        instance: Rc<dyn LoxInstance>,
+        name: &'a Token,
+    },
+    Element {
+        object: RuntimeValue,
+        bracket: &'a Token,
+        index: RuntimeValue,
+    },
+}
@@ -6,13 +6,16 @@ use crate::token::Token;
 
 use super::{
     interpreter_error::{RuntimeError, RuntimeResult},
+    memory::Allocation,
     runtime_value::RuntimeValue,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Environment {
     values: HashMap<String, RuntimeValue>,
     enclosing: Option<Rc<RefCell<Environment>>>,
+    /// Memory charged for a block or call environment, given back with it.
+    allocation: Option<Allocation>,
 }
 
 impl Environment {
@@ -20,6 +23,7 @@ impl Environment {
         Environment {
             values: HashMap::new(),
             enclosing: None,
+            allocation: None,
         }
     }
     /// instantate environment with an `outer_scope` environment
@@ -27,8 +31,13 @@ impl Environment {
         Environment {
             values: HashMap::new(),
             enclosing: Some(outer_scope),
+            allocation: None,
         }
     }
+    pub fn with_allocation(mut self, allocation: Allocation) -> Environment {
+        self.allocation = Some(allocation);
+        self
+    }
     pub fn define(&mut self, name: &str, value: RuntimeValue) {
         self.values.insert(String::from(name), value);
     }
@@ -50,20 +59,19 @@ impl Environment {
         let value = self.env_at_depth(depth, |env| {
             env.values
                 .get(name)
-                .expect(
-                    format!(
+                .unwrap_or_else(|| {
+                    panic!(
                         "Local name {} should be found in the environment at exact depth {}",
                         name, depth
                     )
-                    .as_str(),
-                )
+                })
                 .clone()
         });
         Ok(value)
     }
 
     pub fn assign(&mut self, name: &Token, value: RuntimeValue) -> RuntimeResult {
-        if let Some(_) = self.values.get(name.lexeme.as_str()) {
+        if self.values.contains_key(name.lexeme.as_str()) {
             self.values.insert(name.lexeme.clone(), value.clone());
             Ok(value)
         } else {
@@ -1,4 +1,5 @@
-use crate::token::Token;
+use crate::error::{Diagnostic, Phase};
+use crate::token::{Span, Token, TokenType};
 use std::error;
 use std::fmt;
 
@@ -7,6 +8,8 @@ use super::runtime_value::RuntimeValue;
 #[derive(Debug, Clone)]
 pub enum EarlyReturnReason {
     ReturnFromFunction { return_value: RuntimeValue },
+    BreakLoop,
+    ContinueLoop,
 }
 
 pub trait InterpreterError: error::Error {
@@ -20,12 +23,52 @@ pub trait InterpreterError: error::Error {
     fn message(&self) -> Option<&str> {
         None
     }
+    /// token where the error occured, if known.
+    fn token(&self) -> Option<&Token> {
+        None
+    }
+    /// Where the error occured, if known.
+    fn span(&self) -> Option<Span> {
+        self.token().map(|token| token.span.clone())
+    }
+    /// Diagnostic code, see `Interruption::code` for the errors which aren't `E001`.
+    fn code(&self) -> &'static str {
+        "E001"
+    }
+    /// Set when a limit put by the host stopped the script, rather than the script failing.
+    fn interruption(&self) -> Option<Interruption> {
+        None
+    }
+    /// Value thrown by a `throw` statement, other errors are converted to an `Error`
+    /// instance when caught.
+    fn thrown_value(&self) -> Option<RuntimeValue> {
+        None
+    }
+    /// Lox call stack when the error left the innermost function, see `Interpreter::backtrace`.
+    fn backtrace(&self) -> &[String] {
+        &[]
+    }
+    fn set_backtrace(&mut self, _backtrace: Vec<String>) {}
+}
+
+impl From<&dyn InterpreterError> for Diagnostic {
+    fn from(err: &dyn InterpreterError) -> Self {
+        let message = err.message().map_or_else(|| err.to_string(), String::from);
+        Diagnostic::error(
+            Phase::Runtime,
+            err.code(),
+            &message,
+            err.span().unwrap_or_default(),
+        )
+        .with_backtrace(err.backtrace())
+    }
 }
 
 #[derive(Debug)]
 pub struct RuntimeError {
     pub token: Option<Token>,
     pub message: String,
+    backtrace: Vec<String>,
 }
 
 impl RuntimeError {
@@ -33,12 +76,14 @@ impl RuntimeError {
         Box::new(RuntimeError {
             token: Some(token.clone()),
             message: String::from(message),
+            backtrace: Vec::new(),
         })
     }
     pub fn new_with_message(message: &str) -> Box<RuntimeError> {
         Box::new(RuntimeError {
             token: None,
             message: String::from(message),
+            backtrace: Vec::new(),
         })
     }
 }
@@ -46,7 +91,7 @@ impl RuntimeError {
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(token) = &self.token {
-            write!(f, "[line {}]: {}", token.line, self.message)
+            write!(f, "[{}]: {}", token.span, self.message)
         } else {
             write!(f, "[line unknown]: {}", self.message)
         }
@@ -58,6 +103,15 @@ impl InterpreterError for RuntimeError {
     fn message(&self) -> Option<&str> {
         Some(self.message.as_str())
     }
+    fn token(&self) -> Option<&Token> {
+        self.token.as_ref()
+    }
+    fn backtrace(&self) -> &[String] {
+        &self.backtrace
+    }
+    fn set_backtrace(&mut self, backtrace: Vec<String>) {
+        self.backtrace = backtrace;
+    }
 }
 
 pub type RuntimeResult<T = RuntimeValue> = Result<T, Box<dyn InterpreterError>>;
@@ -79,7 +133,7 @@ impl EarlyReturn {
 
 impl fmt::Display for EarlyReturn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[line {}]: {:?}", self.token.line, self.reason)
+        write!(f, "[{}]: {:?}", self.token.span, self.reason)
     }
 }
 
@@ -92,4 +146,153 @@ impl InterpreterError for EarlyReturn {
     fn early_return_reason(&self) -> Option<EarlyReturnReason> {
         Some(self.reason.clone())
     }
+    fn token(&self) -> Option<&Token> {
+        Some(&self.token)
+    }
+}
+
+/// Value thrown by a `throw` statement, unwinds until a `catch` clause binds it.
+#[derive(Debug)]
+pub struct Exception {
+    pub token: Token,
+    pub value: RuntimeValue,
+    /// Reported when the exception isn't caught.
+    message: String,
+    backtrace: Vec<String>,
+}
+
+impl Exception {
+    pub fn new(token: &Token, value: RuntimeValue) -> Box<Exception> {
+        // Describe an `Error` instance by its message.
+        let description = match &value {
+            RuntimeValue::Instance(instance) => instance
+                .get(&Token::new(
+                    TokenType::IDENTIFIER,
+                    String::from("message"),
+                    Span::default(),
+                ))
+                .filter(|message| matches!(message, RuntimeValue::String(_)))
+                .unwrap_or_else(|| value.clone()),
+            _ => value.clone(),
+        };
+        Box::new(Exception {
+            token: token.clone(),
+            value,
+            message: format!("Uncaught exception: {}", description),
+            backtrace: Vec::new(),
+        })
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]: {}", self.token.span, self.message)
+    }
+}
+
+impl error::Error for Exception {}
+
+impl InterpreterError for Exception {
+    fn message(&self) -> Option<&str> {
+        Some(self.message.as_str())
+    }
+    fn token(&self) -> Option<&Token> {
+        Some(&self.token)
+    }
+    fn thrown_value(&self) -> Option<RuntimeValue> {
+        Some(self.value.clone())
+    }
+    fn backtrace(&self) -> &[String] {
+        &self.backtrace
+    }
+    fn set_backtrace(&mut self, backtrace: Vec<String>) {
+        self.backtrace = backtrace;
+    }
+}
+
+/// Limit put on a run by the host, see `Interpreter::with_step_budget`,
+/// `Interpreter::with_deadline`, `Interpreter::cancel_handle` and
+/// `Interpreter::with_memory_limit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interruption {
+    StepBudgetExhausted,
+    DeadlineExceeded,
+    Cancelled,
+    OutOfMemory,
+}
+
+impl Interruption {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Interruption::StepBudgetExhausted => "E002",
+            Interruption::DeadlineExceeded => "E003",
+            Interruption::Cancelled => "E004",
+            Interruption::OutOfMemory => "E005",
+        }
+    }
+}
+
+impl fmt::Display for Interruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interruption::StepBudgetExhausted => write!(f, "Step budget exhausted."),
+            Interruption::DeadlineExceeded => write!(f, "Deadline exceeded."),
+            Interruption::Cancelled => write!(f, "Execution cancelled."),
+            Interruption::OutOfMemory => write!(f, "Out of memory."),
+        }
+    }
+}
+
+/// Ends a run stopped by the host, a `catch` clause can't handle it.
+#[derive(Debug)]
+pub struct Interrupted {
+    pub reason: Interruption,
+    /// Statement or expression which was about to run, or which needed the memory. A
+    /// native function or a class doesn't know it, the call site is used instead.
+    pub span: Option<Span>,
+    message: String,
+    backtrace: Vec<String>,
+}
+
+impl Interrupted {
+    pub fn new(reason: Interruption, span: Option<Span>) -> Box<Interrupted> {
+        Box::new(Interrupted {
+            reason,
+            span,
+            message: reason.to_string(),
+            backtrace: Vec::new(),
+        })
+    }
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "[{}]: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl error::Error for Interrupted {}
+
+impl InterpreterError for Interrupted {
+    fn message(&self) -> Option<&str> {
+        Some(self.message.as_str())
+    }
+    fn span(&self) -> Option<Span> {
+        self.span.clone()
+    }
+    fn code(&self) -> &'static str {
+        self.reason.code()
+    }
+    fn interruption(&self) -> Option<Interruption> {
+        Some(self.reason)
+    }
+    fn backtrace(&self) -> &[String] {
+        &self.backtrace
+    }
+    fn set_backtrace(&mut self, backtrace: Vec<String>) {
+        self.backtrace = backtrace;
+    }
 }
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::interpreter_error::Interruption;
+
+/// Reading the clock costs more than a step, so the deadline is only checked once every
+/// this many steps.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Stops an interpreter from another thread, see `Interpreter::cancel_handle`.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// The run ends with `Interruption::Cancelled` at its next step. The interpreter stays
+    /// cancelled, later runs end at their first step.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits a host puts on the scripts it runs, checked at every statement and expression.
+#[derive(Debug, Default)]
+pub(super) struct Limits {
+    pub max_steps: Option<u64>,
+    /// Statements executed and expressions evaluated since `max_steps` was set.
+    pub steps: u64,
+    pub deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Limits {
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(Arc::clone(&self.cancelled))
+    }
+
+    /// Count a step, fails once any of the limits is reached.
+    pub fn tick(&mut self) -> Result<(), Interruption> {
+        self.steps += 1;
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(Interruption::Cancelled)
+        } else if self
+            .max_steps
+            .is_some_and(|max_steps| self.steps > max_steps)
+        {
+            Err(Interruption::StepBudgetExhausted)
+        } else if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Err(Interruption::DeadlineExceeded)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -3,8 +3,9 @@ use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 use crate::token::Token;
 
 use super::{
-    interpreter_error::RuntimeResult,
+    interpreter_error::{Interrupted, RuntimeResult},
     lox_function::LoxFunction,
+    memory::Allocation,
     runtime_value::{LoxCallable, LoxInstance, RuntimeValue},
     Interpreter,
 };
@@ -31,16 +32,13 @@ impl LoxClassDefinition {
         }
     }
     pub fn find_method(&self, method_name: &str) -> Option<Rc<LoxFunction>> {
-        self.methods
-            .get(method_name)
-            .map(|method| method.clone())
-            .or_else(|| {
-                if let Some(super_class) = &self.super_class {
-                    super_class.0.find_method(method_name)
-                } else {
-                    None
-                }
-            })
+        self.methods.get(method_name).cloned().or_else(|| {
+            if let Some(super_class) = &self.super_class {
+                super_class.0.find_method(method_name)
+            } else {
+                None
+            }
+        })
     }
 }
 
@@ -76,6 +74,13 @@ impl LoxCallable for LoxClass {
     fn callable_type(&self) -> super::runtime_value::LoxCallableType {
         super::runtime_value::LoxCallableType::Class
     }
+    /// The initializer runs in the frame of the call to the class.
+    fn name(&self) -> String {
+        match self.find_method("init") {
+            Some(initializer) => initializer.name(),
+            None => self.0.name.clone(),
+        }
+    }
     fn arity(&self) -> usize {
         if let Some(initializer) = self.find_method("init") {
             initializer.arity()
@@ -85,7 +90,8 @@ impl LoxCallable for LoxClass {
     }
 
     fn call(&self, interpreter: &mut Interpreter, arguments: Vec<RuntimeValue>) -> RuntimeResult {
-        let instance = ClassInstance::new(self);
+        let allocation = interpreter.allocate(size_of::<ClassInstanceData>(), None)?;
+        let instance = ClassInstance::new(self, allocation);
         if let Some(initializer) = self.find_method("init") {
             let initializer = initializer.bind(&instance);
             initializer.call(interpreter, arguments)
@@ -99,16 +105,19 @@ impl LoxCallable for LoxClass {
 struct ClassInstanceData {
     kclass: Rc<LoxClassDefinition>,
     fields: HashMap<String, RuntimeValue>,
+    /// Grows with every new field.
+    allocation: Allocation,
 }
 
 #[derive(Debug, Clone)]
 pub struct ClassInstance(Rc<RefCell<ClassInstanceData>>);
 
 impl ClassInstance {
-    pub fn new(kclass: &LoxClass) -> ClassInstance {
+    pub fn new(kclass: &LoxClass, allocation: Allocation) -> ClassInstance {
         ClassInstance(Rc::new(RefCell::new(ClassInstanceData {
             kclass: Rc::clone(&kclass.0),
             fields: HashMap::new(),
+            allocation,
         })))
     }
     fn lookup_method(&self, name: &Token) -> Option<Rc<LoxFunction>> {
@@ -123,22 +132,27 @@ impl LoxInstance for ClassInstance {
             .borrow()
             .fields
             .get(&name.lexeme)
-            .map(|field| field.clone())
+            .cloned()
             .or_else(|| {
-                if let Some(method) = self.lookup_method(name) {
-                    Some(RuntimeValue::Callable(Rc::new(method.bind(self))))
-                } else {
-                    None
-                }
+                self.lookup_method(name)
+                    .map(|method| RuntimeValue::Callable(Rc::new(method.bind(self))))
             })
     }
 
-    fn set(&self, name: &Token, value: RuntimeValue) -> RuntimeValue {
-        self.0
-            .borrow_mut()
-            .fields
-            .insert(name.lexeme.clone(), value.clone());
-        value
+    fn set(&self, name: &Token, value: RuntimeValue) -> RuntimeResult {
+        let mut data = self.0.borrow_mut();
+        if !data.fields.contains_key(&name.lexeme) {
+            let bytes = size_of::<(String, RuntimeValue)>() + name.lexeme.len();
+            if let Err(reason) = data.allocation.grow(bytes) {
+                return Err(Interrupted::new(reason, Some(name.span.clone())));
+            }
+        }
+        data.fields.insert(name.lexeme.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn id(&self) -> *const () {
+        Rc::as_ptr(&self.0) as *const ()
     }
 }
 
@@ -1,45 +1,67 @@
 use super::interpreter_error::EarlyReturnReason;
 use super::lox_class::ClassInstance;
+use super::memory::Allocation;
 use super::{environment::Environment, runtime_value::LoxCallable};
 use super::{interpreter_error::RuntimeResult, runtime_value::RuntimeValue, Interpreter};
 use crate::ast::Fun;
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct LoxFunction {
-    declaration: Fun,
+    /// Shared with the tree and with the other functions created from it.
+    declaration: Arc<Fun>,
     closure: Rc<RefCell<Environment>>,
     is_initializer: bool,
+    /// Class the function is a method of.
+    class_name: Option<Rc<str>>,
+    /// Shared with the methods bound from this one.
+    allocation: Option<Rc<Allocation>>,
 }
 
 impl LoxFunction {
     pub fn new(
-        declaration: &Fun,
+        declaration: &Arc<Fun>,
         closure: &Rc<RefCell<Environment>>,
         is_initializer: bool,
     ) -> LoxFunction {
         LoxFunction {
-            declaration: declaration.clone(),
+            declaration: Arc::clone(declaration),
             is_initializer,
             closure: Rc::clone(closure),
+            class_name: None,
+            allocation: None,
         }
     }
+    pub fn in_class(mut self, class_name: &str) -> LoxFunction {
+        self.class_name = Some(Rc::from(class_name));
+        self
+    }
+    pub fn with_allocation(mut self, allocation: Allocation) -> LoxFunction {
+        self.allocation = Some(Rc::new(allocation));
+        self
+    }
     pub fn bind(&self, instance: &ClassInstance) -> LoxFunction {
         let mut environment = Environment::new_with(Rc::clone(&self.closure));
         environment.define("this", RuntimeValue::Instance(Rc::new(instance.clone())));
-        LoxFunction::new(
-            &self.declaration,
-            &Rc::new(RefCell::new(environment)),
-            self.is_initializer,
-        )
+        LoxFunction {
+            declaration: Arc::clone(&self.declaration),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+            class_name: self.class_name.clone(),
+            allocation: self.allocation.clone(),
+        }
     }
 }
 
 impl fmt::Display for LoxFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<fn {}>", self.declaration.name.lexeme)
+        match &self.declaration.name {
+            Some(name) => write!(f, "<fn {}>", name.lexeme),
+            None => write!(f, "<fn anonymous>"),
+        }
     }
 }
 
@@ -47,6 +69,17 @@ impl LoxCallable for LoxFunction {
     fn callable_type(&self) -> super::runtime_value::LoxCallableType {
         super::runtime_value::LoxCallableType::Function
     }
+    fn name(&self) -> String {
+        let name = self
+            .declaration
+            .name
+            .as_ref()
+            .map_or("anonymous", |name| name.lexeme.as_str());
+        match &self.class_name {
+            Some(class_name) => format!("{}.{}", class_name, name),
+            None => String::from(name),
+        }
+    }
     fn arity(&self) -> usize {
         self.declaration.params.len()
     }
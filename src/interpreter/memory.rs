@@ -0,0 +1,97 @@
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use super::interpreter_error::Interruption;
+
+/// Approximate bytes held by the values of the scripts an interpreter runs, see
+/// `Interpreter::with_memory_limit`.
+/// Strings built by the scripts, instances, environments, closures, lists and maps are
+/// charged when created or grown, and given back when the last reference to them is
+/// dropped. Values kept alive by a reference cycle stay charged, like they stay allocated.
+/// The text of string literals and the declarations of functions are shared with the
+/// program, they aren't charged.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryMeter(Rc<MeterState>);
+
+#[derive(Debug, Default)]
+struct MeterState {
+    used: Cell<usize>,
+    limit: Cell<Option<usize>>,
+}
+
+impl MemoryMeter {
+    pub fn used(&self) -> usize {
+        self.0.used.get()
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        self.0.limit.set(Some(limit));
+    }
+
+    /// Charge `bytes`, fails when that goes over the limit.
+    pub fn allocate(&self, bytes: usize) -> Result<Allocation, Interruption> {
+        self.charge(bytes)?;
+        Ok(Allocation {
+            meter: self.clone(),
+            bytes: Cell::new(bytes),
+        })
+    }
+
+    fn charge(&self, bytes: usize) -> Result<(), Interruption> {
+        let used = self.0.used.get().saturating_add(bytes);
+        if self.0.limit.get().is_some_and(|limit| used > limit) {
+            return Err(Interruption::OutOfMemory);
+        }
+        self.0.used.set(used);
+        Ok(())
+    }
+}
+
+/// Bytes charged to a `MemoryMeter`, given back when dropped.
+#[derive(Debug)]
+pub struct Allocation {
+    meter: MemoryMeter,
+    bytes: Cell<usize>,
+}
+
+impl Allocation {
+    /// Charge `bytes` more, fails when that goes over the limit.
+    pub fn grow(&self, bytes: usize) -> Result<(), Interruption> {
+        self.meter.charge(bytes)?;
+        self.bytes.set(self.bytes.get() + bytes);
+        Ok(())
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        let state = &self.meter.0;
+        state.used.set(state.used.get() - self.bytes.get());
+    }
+}
+
+/// A value together with the memory charged for it.
+#[derive(Debug)]
+pub struct Charged<T> {
+    value: T,
+    allocation: Allocation,
+}
+
+impl<T> Charged<T> {
+    pub fn new(value: T, allocation: Allocation) -> Charged<T> {
+        Charged { value, allocation }
+    }
+
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+}
+
+impl<T> Deref for Charged<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
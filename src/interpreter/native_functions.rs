@@ -1,8 +1,10 @@
 use std::fmt;
 use std::time::SystemTime;
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use super::{
-    interpreter_error::{RuntimeError, RuntimeResult},
+    interpreter_error::{Interrupted, RuntimeError, RuntimeResult},
     runtime_value::{LoxCallable, RuntimeValue},
     Interpreter,
 };
@@ -14,11 +16,14 @@ impl LoxCallable for NativeFnClock {
     fn callable_type(&self) -> super::runtime_value::LoxCallableType {
         super::runtime_value::LoxCallableType::NativeFunction
     }
+    fn name(&self) -> String {
+        String::from("clock")
+    }
     fn arity(&self) -> usize {
         0
     }
     fn call(&self, _interpreter: &mut Interpreter, arguments: Vec<RuntimeValue>) -> RuntimeResult {
-        if arguments.len() != 0 {
+        if !arguments.is_empty() {
             Err(RuntimeError::new_with_message(
                 "calling native clock requires zero arguments",
             ))
@@ -38,3 +43,74 @@ impl fmt::Display for NativeFnClock {
         write!(f, "<native fn clock>")
     }
 }
+
+/// Number of elements in a list, entries in a map or graphemes in a string.
+#[derive(Debug)]
+pub struct NativeFnLen;
+
+impl LoxCallable for NativeFnLen {
+    fn callable_type(&self) -> super::runtime_value::LoxCallableType {
+        super::runtime_value::LoxCallableType::NativeFunction
+    }
+    fn name(&self) -> String {
+        String::from("len")
+    }
+    fn arity(&self) -> usize {
+        1
+    }
+    fn call(&self, _interpreter: &mut Interpreter, arguments: Vec<RuntimeValue>) -> RuntimeResult {
+        match arguments.first() {
+            Some(RuntimeValue::List(list)) => Ok(RuntimeValue::Number(list.borrow().len() as f64)),
+            Some(RuntimeValue::Map(map)) => Ok(RuntimeValue::Number(map.borrow().len() as f64)),
+            Some(RuntimeValue::String(value)) => {
+                Ok(RuntimeValue::Number(value.graphemes(true).count() as f64))
+            }
+            _ => Err(RuntimeError::new_with_message(
+                "len() expects a list, a map or a string",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NativeFnLen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn len>")
+    }
+}
+
+/// Appends a value to the end of a list.
+#[derive(Debug)]
+pub struct NativeFnPush;
+
+impl LoxCallable for NativeFnPush {
+    fn callable_type(&self) -> super::runtime_value::LoxCallableType {
+        super::runtime_value::LoxCallableType::NativeFunction
+    }
+    fn name(&self) -> String {
+        String::from("push")
+    }
+    fn arity(&self) -> usize {
+        2
+    }
+    fn call(&self, _interpreter: &mut Interpreter, arguments: Vec<RuntimeValue>) -> RuntimeResult {
+        let mut arguments = arguments.into_iter();
+        match (arguments.next(), arguments.next()) {
+            (Some(RuntimeValue::List(list)), Some(value)) => {
+                if let Err(reason) = list.allocation().grow(size_of::<RuntimeValue>()) {
+                    return Err(Interrupted::new(reason, None));
+                }
+                list.borrow_mut().push(value);
+                Ok(RuntimeValue::Nil)
+            }
+            _ => Err(RuntimeError::new_with_message(
+                "push() expects a list and a value",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NativeFnPush {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn push>")
+    }
+}
@@ -1,12 +1,16 @@
 use super::interpreter_error::{RuntimeError, RuntimeResult};
+use super::memory::{Allocation, Charged};
 use super::Interpreter;
 use crate::ast::LitralValue;
 use crate::token::Token;
 use std::any::Any;
+use std::cell::RefCell;
 use std::cmp::{Ordering, PartialOrd};
+use std::collections::HashMap;
 use std::fmt::{self, Debug};
-use std::ops::{Add, Div, Mul, Neg, Not, Sub};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Deref, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub};
 use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LoxCallableType {
@@ -27,30 +31,168 @@ impl<T: 'static> AsAny for T {
 
 pub trait LoxCallable: AsAny + fmt::Display + Debug {
     fn callable_type(&self) -> LoxCallableType;
+    /// Name shown in backtraces, methods are prefixed with their class.
+    fn name(&self) -> String;
     fn arity(&self) -> usize;
     fn call(&self, interpreter: &mut Interpreter, arguments: Vec<RuntimeValue>) -> RuntimeResult;
 }
 
 pub trait LoxInstance: AsAny + fmt::Display + Debug {
     fn get(&self, name: &Token) -> Option<RuntimeValue>;
-    fn set(&self, name: &Token, value: RuntimeValue) -> RuntimeValue;
+    fn set(&self, name: &Token, value: RuntimeValue) -> RuntimeResult;
+    /// Identity of the instance, the same for every handle to it.
+    fn id(&self) -> *const ();
 }
 
 #[derive(Debug, Clone)]
 pub enum RuntimeValue {
     Number(f64),
-    String(String),
+    String(LoxString),
     Boolean(bool),
     Nil,
     Callable(Rc<dyn LoxCallable>),
     Instance(Rc<dyn LoxInstance>),
+    /// Shared between every copy, so a mutation is seen through all of them.
+    List(Rc<Charged<RefCell<Vec<RuntimeValue>>>>),
+    /// Shared like `List`.
+    Map(Rc<Charged<RefCell<HashMap<MapKey, RuntimeValue>>>>),
+}
+
+/// Text of a string value, shared between its copies and with the literal it comes from.
+/// A string built by a script carries the memory charged for it.
+#[derive(Debug, Clone)]
+pub struct LoxString {
+    text: Arc<str>,
+    /// Only held, the memory is given back when the last copy is dropped.
+    _allocation: Option<Rc<Allocation>>,
+}
+
+impl LoxString {
+    pub fn charged(text: String, allocation: Allocation) -> LoxString {
+        LoxString {
+            text: Arc::from(text),
+            _allocation: Some(Rc::new(allocation)),
+        }
+    }
+}
+
+impl From<String> for LoxString {
+    fn from(text: String) -> Self {
+        LoxString {
+            text: Arc::from(text),
+            _allocation: None,
+        }
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(text: &str) -> Self {
+        LoxString {
+            text: Arc::from(text),
+            _allocation: None,
+        }
+    }
+}
+
+impl Deref for LoxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl PartialOrd for LoxString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.text.partial_cmp(&other.text)
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// A runtime value that can be used as a map key.
+/// Numbers are kept as their bits with `-0` folded into `0`, so that keys that compare
+/// equal also hash the same. NaN never equals itself and isn't allowed as a key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Boolean(bool),
+    Number(u64),
+    String(String),
+}
+
+impl MapKey {
+    /// Position of the variant when keys of different types are sorted.
+    fn rank(&self) -> u8 {
+        match self {
+            MapKey::Nil => 0,
+            MapKey::Boolean(_) => 1,
+            MapKey::Number(_) => 2,
+            MapKey::String(_) => 3,
+        }
+    }
+}
+
+impl TryFrom<&RuntimeValue> for MapKey {
+    type Error = &'static str;
+    fn try_from(value: &RuntimeValue) -> Result<Self, Self::Error> {
+        match value {
+            RuntimeValue::Nil => Ok(MapKey::Nil),
+            RuntimeValue::Boolean(value) => Ok(MapKey::Boolean(*value)),
+            RuntimeValue::Number(value) if value.is_nan() => Err("NaN can't be used as a map key"),
+            // `+ 0.0` turns -0 into 0.
+            RuntimeValue::Number(value) => Ok(MapKey::Number((value + 0.0).to_bits())),
+            RuntimeValue::String(value) => Ok(MapKey::String(value.to_string())),
+            _ => Err("Only strings, numbers, booleans and nil can be used as a map key"),
+        }
+    }
+}
+
+impl From<&MapKey> for RuntimeValue {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Nil => RuntimeValue::Nil,
+            MapKey::Boolean(value) => RuntimeValue::Boolean(*value),
+            MapKey::Number(bits) => RuntimeValue::Number(f64::from_bits(*bits)),
+            MapKey::String(value) => RuntimeValue::String(LoxString::from(value.as_str())),
+        }
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Boolean(lhs), MapKey::Boolean(rhs)) => lhs.cmp(rhs),
+            (MapKey::Number(lhs), MapKey::Number(rhs)) => {
+                f64::from_bits(*lhs).total_cmp(&f64::from_bits(*rhs))
+            }
+            (MapKey::String(lhs), MapKey::String(rhs)) => lhs.cmp(rhs),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Neg for RuntimeValue {
     type Output = RuntimeResult;
     fn neg(self) -> Self::Output {
         if let Self::Number(val) = self {
-            Ok(Self::Number(val * -1.0))
+            Ok(Self::Number(-val))
         } else {
             Err(RuntimeError::new_with_message(
                 "Can't negate anything other than number",
@@ -90,12 +232,113 @@ impl Div for RuntimeValue {
     }
 }
 
+/// Floored modulo, the result takes the sign of the divisor: `-7 % 3` is `2`.
+impl Rem for RuntimeValue {
+    type Output = RuntimeResult;
+    fn rem(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Self::Number(_), Self::Number(0.0)) => {
+                Err(RuntimeError::new_with_message("modulo by zero error"))
+            }
+            (Self::Number(lhs), Self::Number(rhs)) => Ok(Self::Number(floored_rem(lhs, rhs))),
+            _ => Err(RuntimeError::new_with_message(
+                "modulo is allowed only between numbers",
+            )),
+        }
+    }
+}
+
+/// Largest integer `n` such that `n` and `n + 1` are both exactly representable as f64.
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Operand of a bitwise operator, which must be an integer in the safe range.
+fn integer_operand(value: &RuntimeValue) -> RuntimeResult<i64> {
+    match value {
+        RuntimeValue::Number(value) if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER => {
+            Ok(*value as i64)
+        }
+        _ => Err(RuntimeError::new_with_message(
+            "Bitwise operands must be integers between -(2^53 - 1) and 2^53 - 1",
+        )),
+    }
+}
+
+/// Number of bits to shift by, must be between 0 and 63.
+fn shift_amount(value: &RuntimeValue) -> RuntimeResult<u32> {
+    match integer_operand(value)? {
+        amount @ 0..=63 => Ok(amount as u32),
+        _ => Err(RuntimeError::new_with_message(
+            "Shift amount must be between 0 and 63",
+        )),
+    }
+}
+
+impl BitAnd for RuntimeValue {
+    type Output = RuntimeResult;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (integer_operand(&self)?, integer_operand(&rhs)?);
+        Ok(Self::Number((lhs & rhs) as f64))
+    }
+}
+
+impl BitOr for RuntimeValue {
+    type Output = RuntimeResult;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (integer_operand(&self)?, integer_operand(&rhs)?);
+        Ok(Self::Number((lhs | rhs) as f64))
+    }
+}
+
+impl BitXor for RuntimeValue {
+    type Output = RuntimeResult;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (integer_operand(&self)?, integer_operand(&rhs)?);
+        Ok(Self::Number((lhs ^ rhs) as f64))
+    }
+}
+
+impl Shl for RuntimeValue {
+    type Output = RuntimeResult;
+    fn shl(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (integer_operand(&self)?, shift_amount(&rhs)?);
+        // Fits in an i128 since the operand has at most 54 significant bits.
+        let result = (lhs as i128) << rhs;
+        if result.unsigned_abs() > MAX_SAFE_INTEGER as u128 {
+            Err(RuntimeError::new_with_message(
+                "Result of the shift is outside the safe integer range",
+            ))
+        } else {
+            Ok(Self::Number(result as f64))
+        }
+    }
+}
+
+/// Arithmetic shift, the sign of the operand is kept.
+impl Shr for RuntimeValue {
+    type Output = RuntimeResult;
+    fn shr(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (integer_operand(&self)?, shift_amount(&rhs)?);
+        Ok(Self::Number((lhs >> rhs) as f64))
+    }
+}
+
+fn floored_rem(lhs: f64, rhs: f64) -> f64 {
+    let rem = lhs % rhs;
+    if rem != 0.0 && (rem < 0.0) != (rhs < 0.0) {
+        rem + rhs
+    } else {
+        rem
+    }
+}
+
 impl Add for RuntimeValue {
     type Output = RuntimeResult;
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Self::Number(lhs), Self::Number(rhs)) => Ok(Self::Number(lhs + rhs)),
-            (Self::String(lhs), Self::String(rhs)) => Ok(Self::String(format!("{}{}", lhs, rhs))),
+            (Self::String(lhs), Self::String(rhs)) => {
+                Ok(Self::String(LoxString::from(format!("{}{}", lhs, rhs))))
+            }
             _ => Err(RuntimeError::new_with_message(
                 "addition is allowed only between numbers",
             )),
@@ -135,18 +378,14 @@ impl PartialEq for RuntimeValue {
             (Self::Number(lhs), Self::Number(rhs)) => lhs == rhs,
             (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
             (Self::Nil, Self::Nil) => true,
+            // Everything else is equal only to itself.
+            (Self::Callable(lhs), Self::Callable(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Instance(lhs), Self::Instance(rhs)) => lhs.id() == rhs.id(),
+            (Self::List(lhs), Self::List(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Map(lhs), Self::Map(rhs)) => Rc::ptr_eq(lhs, rhs),
             _ => false,
         }
     }
-    fn ne(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::String(lhs), Self::String(rhs)) => lhs != rhs,
-            (Self::Number(lhs), Self::Number(rhs)) => lhs != rhs,
-            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs != rhs,
-            (Self::Nil, Self::Nil) => false,
-            _ => true,
-        }
-    }
 }
 
 impl PartialOrd for RuntimeValue {
@@ -202,6 +441,88 @@ impl RuntimeValue {
             _ => Self::Boolean(true),
         }
     }
+
+    /// Division rounded towards negative infinity, consistent with `%` so that
+    /// `a == b * (a // b) + a % b`.
+    pub fn floor_div(self, rhs: Self) -> RuntimeResult {
+        match (self, rhs) {
+            (Self::Number(_), Self::Number(0.0)) => {
+                Err(RuntimeError::new_with_message("divide by zero error"))
+            }
+            (Self::Number(lhs), Self::Number(rhs)) => {
+                Ok(Self::Number(((lhs - floored_rem(lhs, rhs)) / rhs).round()))
+            }
+            _ => Err(RuntimeError::new_with_message(
+                "integer division is allowed only between numbers",
+            )),
+        }
+    }
+
+    /// Bitwise complement, `Not` is already taken by the logical `!`.
+    pub fn bit_not(self) -> RuntimeResult {
+        Ok(Self::Number(!integer_operand(&self)? as f64))
+    }
+
+    pub fn pow(self, rhs: Self) -> RuntimeResult {
+        match (self, rhs) {
+            (Self::Number(lhs), Self::Number(rhs)) => Ok(Self::Number(lhs.powf(rhs))),
+            _ => Err(RuntimeError::new_with_message(
+                "exponentiation is allowed only between numbers",
+            )),
+        }
+    }
+}
+
+impl RuntimeValue {
+    /// Write the value, `printing` holds the lists and maps being written around it. They
+    /// share their storage so they can contain themselves, one met again is written `[...]`
+    /// or `{...}`.
+    fn write_nested(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        printing: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match self {
+            RuntimeValue::List(elements) => {
+                let ptr = Rc::as_ptr(elements) as *const ();
+                if printing.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                printing.push(ptr);
+                write!(f, "[")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write_nested(f, printing)?;
+                }
+                printing.pop();
+                write!(f, "]")
+            }
+            RuntimeValue::Map(entries) => {
+                let ptr = Rc::as_ptr(entries) as *const ();
+                if printing.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                printing.push(ptr);
+                // Sorted by key, so that the output doesn't depend on the hash order.
+                let entries = entries.borrow();
+                let mut keys: Vec<&MapKey> = entries.keys().collect();
+                keys.sort();
+                write!(f, "{{")?;
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", RuntimeValue::from(key))?;
+                    entries[key].write_nested(f, printing)?;
+                }
+                printing.pop();
+                write!(f, "}}")
+            }
+            _ => write!(f, "{}", self),
+        }
+    }
 }
 
 impl fmt::Display for RuntimeValue {
@@ -214,6 +535,7 @@ impl fmt::Display for RuntimeValue {
             Boolean(value) => write!(f, "{}", value),
             Callable(ptr) => write!(f, "{}", ptr),
             Instance(ptr) => write!(f, "{}", ptr),
+            List(_) | Map(_) => self.write_nested(f, &mut Vec::new()),
         }
     }
 }
@@ -222,7 +544,10 @@ impl From<LitralValue> for RuntimeValue {
     fn from(value: LitralValue) -> Self {
         match value {
             LitralValue::NUMBER(litral_value) => RuntimeValue::Number(litral_value),
-            LitralValue::STRING(litral_value) => RuntimeValue::String(litral_value),
+            LitralValue::STRING(text) => RuntimeValue::String(LoxString {
+                text,
+                _allocation: None,
+            }),
             LitralValue::True => RuntimeValue::Boolean(true),
             LitralValue::False => RuntimeValue::Boolean(false),
             LitralValue::Nil => RuntimeValue::Nil,
@@ -232,20 +557,58 @@ impl From<LitralValue> for RuntimeValue {
 
 impl From<&RuntimeValue> for bool {
     fn from(value: &RuntimeValue) -> Self {
-        if let RuntimeValue::Boolean(true) = value.is_truthy() {
-            true
-        } else {
-            false
-        }
+        matches!(value.is_truthy(), RuntimeValue::Boolean(true))
     }
 }
 
 impl From<RuntimeValue> for bool {
     fn from(value: RuntimeValue) -> Self {
-        if let RuntimeValue::Boolean(true) = value.is_truthy() {
-            true
-        } else {
-            false
+        bool::from(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::memory::MemoryMeter;
+
+    fn list(elements: Vec<RuntimeValue>) -> RuntimeValue {
+        let allocation = MemoryMeter::default().allocate(0).unwrap();
+        RuntimeValue::List(Rc::new(Charged::new(RefCell::new(elements), allocation)))
+    }
+
+    #[test]
+    fn list_containing_itself_is_printed_once() {
+        let value = list(vec![RuntimeValue::Number(1.0)]);
+        if let RuntimeValue::List(elements) = &value {
+            elements.borrow_mut().push(value.clone());
+        }
+        assert_eq!(value.to_string(), "[1, [...]]");
+        // Break the cycle, so that the list is freed.
+        if let RuntimeValue::List(elements) = &value {
+            elements.borrow_mut().clear();
         }
     }
+
+    #[test]
+    fn map_containing_itself_is_printed_once() {
+        let allocation = MemoryMeter::default().allocate(0).unwrap();
+        let entries = HashMap::from([(MapKey::String(String::from("a")), list(Vec::new()))]);
+        let value = RuntimeValue::Map(Rc::new(Charged::new(RefCell::new(entries), allocation)));
+        if let RuntimeValue::Map(entries) = &value {
+            let key = MapKey::String(String::from("self"));
+            entries.borrow_mut().insert(key, value.clone());
+        }
+        assert_eq!(value.to_string(), "{a: [], self: {...}}");
+        if let RuntimeValue::Map(entries) = &value {
+            entries.borrow_mut().clear();
+        }
+    }
+
+    #[test]
+    fn list_repeated_without_cycle_is_printed_in_full() {
+        let inner = list(vec![RuntimeValue::Number(2.0)]);
+        let value = list(vec![inner.clone(), inner]);
+        assert_eq!(value.to_string(), "[[2], [2]]");
+    }
 }
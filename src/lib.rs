@@ -5,3 +5,74 @@ pub mod parser;
 pub mod resolver;
 pub mod scanner;
 pub mod token;
+
+use ast::Stmt;
+use error::{CompileResult, Diagnostic};
+use interpreter::{interpreter_error::Interruption, Interpreter};
+use parser::Parser;
+use resolver::Resolver;
+use scanner::Scanner;
+
+/// Result of running a piece of Lox source.
+#[derive(Debug)]
+pub enum RunOutcome {
+    Success,
+    /// Scanning, parsing or resolving failed, nothing was executed.
+    CompileError(Vec<Diagnostic>),
+    RuntimeError(Diagnostic),
+    /// A limit put by the host stopped the run, see `InterpreterError::interruption`.
+    Interrupted(Interruption, Diagnostic),
+}
+
+impl RunOutcome {
+    /// Process exit status following sysexits.h, like the reference jlox.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RunOutcome::Success => 0,
+            RunOutcome::CompileError(_) => 65, // EX_DATAERR
+            RunOutcome::RuntimeError(_) => 70, // EX_SOFTWARE
+            RunOutcome::Interrupted(..) => 75, // EX_TEMPFAIL
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            RunOutcome::Success => &[],
+            RunOutcome::CompileError(diagnostics) => diagnostics,
+            RunOutcome::RuntimeError(diagnostic) | RunOutcome::Interrupted(_, diagnostic) => {
+                std::slice::from_ref(diagnostic)
+            }
+        }
+    }
+}
+
+/// Scan, parse and resolve `source`. `file` is only used to name the source in spans.
+pub fn compile(source: String, file: Option<&str>) -> CompileResult<Vec<Stmt>> {
+    let mut scanner = match file {
+        Some(file) => Scanner::new_with_file(source, file),
+        None => Scanner::new(source),
+    };
+    let tokens = scanner.scan_tokens()?;
+    let mut stmts = Parser::new(tokens).parse()?;
+    Resolver::new().resolve_stmts(&mut stmts)?;
+    Ok(stmts)
+}
+
+/// Execute resolved statements, see [`compile`].
+pub fn execute(stmts: &[Stmt], interpreter: &mut Interpreter) -> RunOutcome {
+    match interpreter.interpret(stmts) {
+        Ok(()) => RunOutcome::Success,
+        Err(err) => match err.interruption() {
+            Some(reason) => RunOutcome::Interrupted(reason, Diagnostic::from(err.as_ref())),
+            None => RunOutcome::RuntimeError(Diagnostic::from(err.as_ref())),
+        },
+    }
+}
+
+/// Compile and execute `source` with `interpreter`.
+pub fn run(source: String, file: Option<&str>, interpreter: &mut Interpreter) -> RunOutcome {
+    match compile(source, file) {
+        Ok(stmts) => execute(&stmts, interpreter),
+        Err(diagnostics) => RunOutcome::CompileError(diagnostics),
+    }
+}
@@ -1,78 +1,210 @@
-use jlox::error::error_at_runtime;
+use jlox::ast::{
+    printer::{AstPrinter, SourcePrinter},
+    Stmt,
+};
+use jlox::error::{json::JsonEmitter, renderer::Renderer, Diagnostic};
 use jlox::interpreter::Interpreter;
-use jlox::parser::Parser;
-use jlox::resolver::Resolver;
 use jlox::scanner::Scanner;
+use jlox::RunOutcome;
 use std::env;
 use std::error::Error;
 use std::fs::File;
-use std::io::{stdin, stdout, Read, Write};
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
 use std::process::ExitCode;
 
+const USAGE: &str =
+    "Usage: jlox [--diagnostics=human|json] [--dump-tokens | --dump-ast[=json|lisp|source]] [script]";
+
+// Exit codes from sysexits.h
+const EX_USAGE: u8 = 64;
+const EX_NOINPUT: u8 = 66;
+
+/// How diagnostics are written to stderr.
+#[derive(Clone, Copy, PartialEq)]
+enum DiagnosticsFormat {
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+/// What to do with the source.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Run,
+    /// Print the scanner output.
+    DumpTokens,
+    /// Print the resolved statements.
+    DumpAst,
+    DumpAstJson,
+    /// Print the statements in parenthesized prefix form.
+    DumpAstLisp,
+    /// Print the statements back as Lox source.
+    DumpAstSource,
+}
+
+struct Options {
+    diagnostics: DiagnosticsFormat,
+    mode: Mode,
+    script: Option<String>,
+}
+
 fn main() -> ExitCode {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        eprintln!("Usage: jlox [script]");
-        ExitCode::from(ExitCode::FAILURE)
-    } else if args.len() == 2 {
-        match run_file(&args[1]) {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(EX_USAGE);
+        }
+    };
+    match &options.script {
+        Some(script) => match run_file(script, &options) {
+            Ok(outcome) => ExitCode::from(outcome.exit_code()),
             Err(err) => {
-                eprintln!("Erred out {:?}", err);
-                ExitCode::FAILURE
+                eprintln!("Unable to read {}: {}", script, err);
+                ExitCode::from(EX_NOINPUT)
             }
+        },
+        None => match run_prompt(&options) {
             Ok(()) => ExitCode::SUCCESS,
-        }
-    } else {
-        match run_prompt() {
             Err(err) => {
                 eprintln!("Erred out {:?}", err);
                 ExitCode::FAILURE
             }
-            Ok(()) => ExitCode::SUCCESS,
+        },
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        diagnostics: DiagnosticsFormat::Human,
+        mode: Mode::Run,
+        script: None,
+    };
+    for arg in args {
+        if let Some(format) = arg.strip_prefix("--diagnostics=") {
+            options.diagnostics = match format {
+                "human" => DiagnosticsFormat::Human,
+                "json" => DiagnosticsFormat::Json,
+                _ => return Err(format!("Unknown diagnostics format '{}'", format)),
+            };
+        } else if arg == "--dump-tokens" {
+            options.mode = Mode::DumpTokens;
+        } else if arg == "--dump-ast" {
+            options.mode = Mode::DumpAst;
+        } else if arg == "--dump-ast=json" {
+            options.mode = Mode::DumpAstJson;
+        } else if arg == "--dump-ast=lisp" {
+            options.mode = Mode::DumpAstLisp;
+        } else if arg == "--dump-ast=source" {
+            options.mode = Mode::DumpAstSource;
+        } else if arg.starts_with("--") {
+            return Err(format!("Unknown option '{}'", arg));
+        } else if options.script.is_none() {
+            options.script = Some(arg);
+        } else {
+            return Err(String::from("Only one script can be run at a time"));
         }
     }
+    Ok(options)
 }
 
-fn run_file(file_path: &String) -> Result<(), Box<dyn Error>> {
+fn run_file(file_path: &str, options: &Options) -> Result<RunOutcome, Box<dyn Error>> {
     let mut file = File::open(file_path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
     let mut interpreter = Interpreter::new();
-    run(contents, &mut interpreter);
-    Ok(())
+    Ok(run(contents, Some(file_path), &mut interpreter, options))
 }
 
-fn run_prompt() -> Result<(), Box<dyn Error>> {
+fn run_prompt(options: &Options) -> Result<(), Box<dyn Error>> {
     let mut interpreter = Interpreter::new();
-    loop {
+    for entry in 1.. {
         print!("> ");
         stdout().flush()?;
 
         let mut line = String::new();
-        stdin().read_line(&mut line)?;
+        if stdin().read_line(&mut line)? == 0 {
+            // end of input
+            println!();
+            return Ok(());
+        }
         let mut line = line.trim().to_string();
         if !line.ends_with(";") {
             line = format!("print {};", line);
         }
-        run(line, &mut interpreter);
+        // Each entry is its own source, named so that a span into an earlier one isn't
+        // shown against this one.
+        let name = format!("<prompt-{}>", entry);
+        run(line, Some(&name), &mut interpreter, options);
     }
+    Ok(())
 }
 
-fn run(source: String, interpreter: &mut Interpreter) {
-    let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
-    //println!("{:#?}", tokens);
-    let mut parser = Parser::new(tokens);
-    let mut stmts = parser.parse();
-    //println!("{:#?}", stmts);
-    if parser.get_num_of_parser_errors() == 0 {
-        let mut resolver = Resolver::new();
-        resolver.resolve_stmts(&mut stmts);
-        if resolver.get_num_of_resolver_errs() == 0 {
-            println!("{:#?}", stmts);
-            if let Err(err) = interpreter.interpret(&stmts) {
-                error_at_runtime(err);
+fn run(
+    source: String,
+    file: Option<&str>,
+    interpreter: &mut Interpreter,
+    options: &Options,
+) -> RunOutcome {
+    let report_source = source.clone();
+    let outcome = match options.mode {
+        Mode::Run => jlox::run(source, file, interpreter),
+        Mode::DumpTokens => dump_tokens(source, file),
+        Mode::DumpAst | Mode::DumpAstJson | Mode::DumpAstLisp | Mode::DumpAstSource => {
+            match jlox::compile(source, file) {
+                Ok(stmts) => {
+                    dump_ast(&stmts, options.mode);
+                    RunOutcome::Success
+                }
+                Err(diagnostics) => RunOutcome::CompileError(diagnostics),
             }
         }
+    };
+    report(&report_source, file, outcome.diagnostics(), options);
+    outcome
+}
+
+fn dump_ast(stmts: &[Stmt], mode: Mode) {
+    match mode {
+        Mode::DumpAstJson => println!(
+            "{}",
+            serde_json::to_string_pretty(stmts).expect("AST should serialize to JSON")
+        ),
+        Mode::DumpAstLisp => println!("{}", AstPrinter::print(stmts)),
+        Mode::DumpAstSource => print!("{}", SourcePrinter::print(stmts)),
+        _ => println!("{:#?}", stmts),
+    }
+}
+
+fn dump_tokens(source: String, file: Option<&str>) -> RunOutcome {
+    let mut scanner = match file {
+        Some(file) => Scanner::new_with_file(source, file),
+        None => Scanner::new(source),
+    };
+    match scanner.scan_tokens() {
+        Ok(tokens) => {
+            tokens
+                .iter()
+                .for_each(|token| println!("{} {}", token.span, token));
+            RunOutcome::Success
+        }
+        Err(diagnostics) => RunOutcome::CompileError(diagnostics),
+    }
+}
+
+fn report(source: &str, file: Option<&str>, diagnostics: &[Diagnostic], options: &Options) {
+    match options.diagnostics {
+        DiagnosticsFormat::Human => {
+            let renderer = Renderer::new(source, stdout().is_terminal()).with_file(file);
+            diagnostics
+                .iter()
+                .for_each(|diagnostic| eprint!("{}", renderer.render(diagnostic)));
+        }
+        DiagnosticsFormat::Json => {
+            let emitter = JsonEmitter::new(source).with_file(file);
+            diagnostics
+                .iter()
+                .for_each(|diagnostic| eprintln!("{}", emitter.emit(diagnostic)));
+        }
     }
 }
@@ -1,32 +1,31 @@
 use crate::{
     ast::Expr,
-    ast::{Fun, LitralValue, Stmt},
-    token::{Token, TokenType},
+    ast::{Catch, Fun, LitralValue, Stmt},
+    error::{CompileResult, Diagnostic, Phase},
+    token::{Span, Token, TokenType},
 };
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
 
 /// ToDo:: refractor using parser combinators
 pub struct Parser<'a> {
-    tokens: &'a Vec<Token>,
+    tokens: &'a [Token],
     current: usize,
-    num_of_parser_errs: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &Vec<Token>) -> Parser {
+    pub fn new(tokens: &[Token]) -> Parser<'_> {
         Parser {
             tokens,
             current: 0,
-            num_of_parser_errs: 0,
+            diagnostics: Vec::new(),
         }
     }
 
-    pub fn get_num_of_parser_errors(&self) -> usize {
-        self.num_of_parser_errs
-    }
-
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    /// Parse all the tokens, on failure returns every error found.
+    pub fn parse(&mut self) -> CompileResult<Vec<Stmt>> {
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.is_at_end() {
             let stmt = self.declaration();
@@ -34,19 +33,19 @@ impl<'a> Parser<'a> {
                 statements.push(stmt);
             }
         }
-        statements
+        if self.diagnostics.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
     }
 
-    fn error(&mut self, err: ParserError) {
-        self.num_of_parser_errs += 1;
-        crate::error::error_at_compiler(&err);
+    fn error(&mut self, err: &ParserError) {
+        self.diagnostics.push(err.into());
     }
 
     fn is_at_end(&self) -> bool {
-        match self.peek().token_type {
-            TokenType::EOF => true,
-            _ => false,
-        }
+        matches!(self.peek().token_type, TokenType::EOF)
     }
 
     fn advance(&mut self) -> &Token {
@@ -73,6 +72,25 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Like `check` but looks one token past the current one.
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => &token.token_type == token_type,
+            None => false,
+        }
+    }
+
+    /// At the start of a statement '{' opens a block, unless it is followed by a single
+    /// token key and ':', e.g. `{"key": value}[key];`.
+    fn is_map_literal(&self) -> bool {
+        match self.tokens.get(self.current + 2) {
+            Some(token) => {
+                self.check(&TokenType::LEFT_BRACE) && token.token_type == TokenType::COLON
+            }
+            None => false,
+        }
+    }
+
     fn matches(&mut self, token_types: &[TokenType]) -> bool {
         for token_type in token_types {
             if self.check(token_type) {
@@ -81,21 +99,27 @@ impl<'a> Parser<'a> {
             }
         }
 
-        return false;
+        false
     }
 
     fn consume(&mut self, token_type: &TokenType, message: &str) -> ParserResult<&Token> {
         if self.check(token_type) {
             Ok(self.advance())
         } else {
-            Err(ParserError::new(self.peek(), message))
+            let found = match self.peek().token_type {
+                TokenType::EOF => String::from("found end of file"),
+                _ => format!("found '{}'", self.peek().lexeme),
+            };
+            Err(ParserError::new("P001", self.peek(), message).with_label(&found))
         }
     }
 
     fn declaration(&mut self) -> Option<Stmt> {
         let stmt = if self.matches(&[TokenType::CLASS]) {
             self.class_declaration()
-        } else if self.matches(&[TokenType::FUN]) {
+        } else if self.check(&TokenType::FUN) && self.check_next(&TokenType::IDENTIFIER) {
+            // Without a name it is a lambda, parsed as an expression statement.
+            self.advance();
             self.function("function")
         } else if self.matches(&[TokenType::VAR]) {
             self.var_declaration()
@@ -104,7 +128,7 @@ impl<'a> Parser<'a> {
         };
 
         if let Err(parse_err) = stmt {
-            self.error(parse_err);
+            self.error(&parse_err);
             self.synchronize();
             None
         } else {
@@ -152,11 +176,18 @@ impl<'a> Parser<'a> {
             &TokenType::LEFT_PARAN,
             format!("Expect '(' after {} name", kind).as_str(),
         )?;
+        let fun = self.function_body(Some(name), kind)?;
+        Ok(Stmt::Function(Arc::new(fun)))
+    }
+
+    /// Parameters and body of a function, after the opening '('.
+    fn function_body(&mut self, name: Option<Token>, kind: &str) -> ParserResult<Fun> {
         let mut params = Vec::new();
         if !self.check(&TokenType::RIGHT_PARAN) {
             loop {
                 if params.len() >= 255 {
-                    self.error(ParserError::new(
+                    self.error(&ParserError::new(
+                        "P003",
                         self.peek(),
                         format!("Can't allow more than 255 params for a {}", kind).as_str(),
                     ))
@@ -180,7 +211,7 @@ impl<'a> Parser<'a> {
             format!("Expect '{{' before start of a {} body", kind).as_str(),
         )?;
         let body = self.block()?;
-        Ok(Stmt::Function(Fun { name, params, body }))
+        Ok(Fun { name, params, body })
     }
 
     fn var_declaration(&mut self) -> ParserResult<Stmt> {
@@ -209,7 +240,26 @@ impl<'a> Parser<'a> {
             self.for_statement()
         } else if self.matches(&[TokenType::RETURN]) {
             self.return_statement()
-        } else if self.matches(&[TokenType::LEFT_BRACE]) {
+        } else if self.matches(&[TokenType::BREAK]) {
+            let keyword = self.previous().clone();
+            self.consume(&TokenType::SEMICOLON, "Expect ';' after 'break'")?;
+            Ok(Stmt::Break { keyword })
+        } else if self.matches(&[TokenType::CONTINUE]) {
+            let keyword = self.previous().clone();
+            self.consume(&TokenType::SEMICOLON, "Expect ';' after 'continue'")?;
+            Ok(Stmt::Continue { keyword })
+        } else if self.matches(&[TokenType::THROW]) {
+            let keyword = self.previous().clone();
+            let value = self.expression()?;
+            self.consume(&TokenType::SEMICOLON, "Expect ';' after thrown value")?;
+            Ok(Stmt::Throw {
+                keyword,
+                value: *value,
+            })
+        } else if self.matches(&[TokenType::TRY]) {
+            self.try_statement()
+        } else if self.check(&TokenType::LEFT_BRACE) && !self.is_map_literal() {
+            self.advance();
             let statements = self.block()?;
             Ok(Stmt::Block { statements })
         } else {
@@ -217,6 +267,46 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn try_statement(&mut self) -> ParserResult<Stmt> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LEFT_BRACE, "Expect '{' after 'try'")?;
+        let body = self.block()?;
+
+        let mut catch = None;
+        if self.matches(&[TokenType::CATCH]) {
+            self.consume(&TokenType::LEFT_PARAN, "Expect '(' after 'catch'")?;
+            let name = self
+                .consume(&TokenType::IDENTIFIER, "Expect exception variable name")?
+                .clone();
+            self.consume(
+                &TokenType::RIGHT_PARAN,
+                "Expect ')' after exception variable name",
+            )?;
+            self.consume(&TokenType::LEFT_BRACE, "Expect '{' before catch body")?;
+            catch = Some(Catch {
+                name,
+                body: self.block()?,
+            });
+        }
+
+        let mut finally = None;
+        if catch.is_none() || self.check(&TokenType::FINALLY) {
+            self.consume(
+                &TokenType::FINALLY,
+                "Expect 'catch' or 'finally' after try block",
+            )?;
+            self.consume(&TokenType::LEFT_BRACE, "Expect '{' after 'finally'")?;
+            finally = Some(self.block()?);
+        }
+
+        Ok(Stmt::Try {
+            keyword,
+            body,
+            catch,
+            finally,
+        })
+    }
+
     fn if_statement(&mut self) -> ParserResult<Stmt> {
         self.consume(&TokenType::LEFT_PARAN, "Expect ( after if")?;
         let condition = self.expression()?;
@@ -242,19 +332,20 @@ impl<'a> Parser<'a> {
         Ok(Stmt::WhileStmt {
             condition: *condition,
             body: Box::new(body),
+            increment: None,
         })
     }
 
     fn for_statement(&mut self) -> ParserResult<Stmt> {
+        let keyword = self.previous().clone();
         self.consume(&TokenType::LEFT_PARAN, "Expect '(' after for")?;
-        let initializer;
-        if self.matches(&[TokenType::SEMICOLON]) {
-            initializer = None;
+        let initializer = if self.matches(&[TokenType::SEMICOLON]) {
+            None
         } else if self.matches(&[TokenType::VAR]) {
-            initializer = Some(self.var_declaration()?);
+            Some(self.var_declaration()?)
         } else {
-            initializer = Some(self.expression_statement()?);
-        }
+            Some(self.expression_statement()?)
+        };
 
         let mut condition = None;
         if !self.check(&TokenType::SEMICOLON) {
@@ -266,24 +357,16 @@ impl<'a> Parser<'a> {
             increment = Some(*self.expression()?);
         }
         self.consume(&TokenType::RIGHT_PARAN, "Expect  matching ')' in for loop")?;
-        let mut body = self.statement()?;
+        let body = self.statement()?;
 
-        if let Some(increment) = increment {
-            body = Stmt::Block {
-                statements: vec![
-                    body,
-                    Stmt::ExpressionStmt {
-                        expression: increment,
-                    },
-                ],
-            }
-        }
-        if let None = condition {
-            condition = Some(Expr::Litral(LitralValue::True));
-        }
-        body = Stmt::WhileStmt {
-            condition: condition.unwrap(),
+        let condition = condition.unwrap_or(Expr::Litral {
+            value: LitralValue::True,
+            span: keyword.span,
+        });
+        let mut body = Stmt::WhileStmt {
+            condition,
             body: Box::new(body),
+            increment,
         };
 
         if let Some(initializer) = initializer {
@@ -334,7 +417,7 @@ impl<'a> Parser<'a> {
     }
 
     fn assignment(&mut self) -> ParserBoxdResult<Expr> {
-        let expr = self.or()?;
+        let expr = self.conditional()?;
 
         if self.matches(&[TokenType::EQUAL]) {
             let equals = self.previous().clone();
@@ -352,12 +435,109 @@ impl<'a> Parser<'a> {
                     name,
                     value,
                 }));
+            } else if let Expr::Index {
+                object,
+                bracket,
+                index,
+            } = *expr
+            {
+                return Ok(Box::new(Expr::IndexSet {
+                    object,
+                    bracket,
+                    index,
+                    value,
+                }));
             } else {
-                self.error(ParserError::new(&equals, "Invalid assignment target"));
+                self.error(&ParserError::new(
+                    "P002",
+                    &equals,
+                    "Invalid assignment target",
+                ));
+            }
+        } else if self.matches(&[
+            TokenType::PLUS_EQUAL,
+            TokenType::MINUS_EQUAL,
+            TokenType::STAR_EQUAL,
+            TokenType::SLASH_EQUAL,
+            TokenType::PERCENT_EQUAL,
+        ]) {
+            let operator = self.previous().clone();
+            let value = self.assignment()?;
+
+            if Self::is_assignment_target(&expr) {
+                return Ok(Box::new(Expr::CompoundAssign {
+                    target: expr,
+                    operator,
+                    value,
+                }));
             }
+            self.error(&ParserError::new(
+                "P002",
+                &operator,
+                "Invalid assignment target",
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    fn is_assignment_target(expr: &Expr) -> bool {
+        matches!(
+            expr,
+            Expr::Variable { .. } | Expr::Get { .. } | Expr::Index { .. }
+        )
+    }
+
+    fn increment(&mut self, target: Box<Expr>, operator: Token, prefix: bool) -> Box<Expr> {
+        if Self::is_assignment_target(&target) {
+            Box::new(Expr::Increment {
+                target,
+                operator,
+                prefix,
+            })
+        } else {
+            self.error(&ParserError::new(
+                "P002",
+                &operator,
+                format!("Invalid target for '{}'", operator.lexeme).as_str(),
+            ));
+            target
+        }
+    }
+
+    /// Right associative, `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn conditional(&mut self) -> ParserBoxdResult<Expr> {
+        let condition = self.coalesce()?;
+
+        if self.matches(&[TokenType::QUESTION]) {
+            let then_branch = self.expression()?;
+            self.consume(
+                &TokenType::COLON,
+                "Expect ':' after then branch of conditional expression",
+            )?;
+            let else_branch = self.conditional()?;
+            return Ok(Box::new(Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            }));
         }
+        Ok(condition)
+    }
+
+    fn coalesce(&mut self) -> ParserBoxdResult<Expr> {
+        let mut expr = self.or()?;
 
-        return Ok(expr);
+        while self.matches(&[TokenType::QUESTION_QUESTION]) {
+            let operator = self.previous().clone();
+            let right = self.or()?;
+            expr = Box::new(Expr::Coalesce {
+                left: expr,
+                operator,
+                right,
+            })
+        }
+        Ok(expr)
     }
 
     fn or(&mut self) -> ParserBoxdResult<Expr> {
@@ -393,9 +573,71 @@ impl<'a> Parser<'a> {
     }
 
     fn equality(&mut self) -> ParserBoxdResult<Expr> {
-        let mut expr = self.comparison()?;
+        let mut expr = self.bit_or()?;
 
         while self.matches(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
+            let operator = self.previous().clone();
+            let right = self.bit_or()?;
+            expr = Box::new(Expr::Binary {
+                left: expr,
+                operator,
+                right,
+            })
+        }
+        Ok(expr)
+    }
+
+    /// The bitwise operators bind tighter than equality, so `flags & MASK == 0`
+    /// tests the masked bits.
+    fn bit_or(&mut self) -> ParserBoxdResult<Expr> {
+        let mut expr = self.bit_xor()?;
+
+        while self.matches(&[TokenType::PIPE]) {
+            let operator = self.previous().clone();
+            let right = self.bit_xor()?;
+            expr = Box::new(Expr::Binary {
+                left: expr,
+                operator,
+                right,
+            })
+        }
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> ParserBoxdResult<Expr> {
+        let mut expr = self.bit_and()?;
+
+        while self.matches(&[TokenType::CARET]) {
+            let operator = self.previous().clone();
+            let right = self.bit_and()?;
+            expr = Box::new(Expr::Binary {
+                left: expr,
+                operator,
+                right,
+            })
+        }
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> ParserBoxdResult<Expr> {
+        let mut expr = self.shift()?;
+
+        while self.matches(&[TokenType::AMPERSAND]) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+            expr = Box::new(Expr::Binary {
+                left: expr,
+                operator,
+                right,
+            })
+        }
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> ParserBoxdResult<Expr> {
+        let mut expr = self.comparison()?;
+
+        while self.matches(&[TokenType::LESS_LESS, TokenType::GREATER_GREATER]) {
             let operator = self.previous().clone();
             let right = self.comparison()?;
             expr = Box::new(Expr::Binary {
@@ -442,7 +684,12 @@ impl<'a> Parser<'a> {
     fn factor(&mut self) -> ParserBoxdResult<Expr> {
         let mut expr = self.unary()?;
 
-        while self.matches(&[TokenType::STAR, TokenType::SLASH]) {
+        while self.matches(&[
+            TokenType::STAR,
+            TokenType::SLASH,
+            TokenType::SLASH_SLASH,
+            TokenType::PERCENT,
+        ]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             expr = Box::new(Expr::Binary {
@@ -455,13 +702,44 @@ impl<'a> Parser<'a> {
     }
 
     fn unary(&mut self) -> ParserBoxdResult<Expr> {
-        if self.matches(&[TokenType::BANG, TokenType::MINUS]) {
+        if self.matches(&[TokenType::BANG, TokenType::MINUS, TokenType::TILDE]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             Ok(Box::new(Expr::Unary { operator, right }))
+        } else if self.matches(&[TokenType::PLUS_PLUS, TokenType::MINUS_MINUS]) {
+            let operator = self.previous().clone();
+            let target = self.unary()?;
+            Ok(self.increment(target, operator, true))
         } else {
-            self.call()
+            self.power()
+        }
+    }
+
+    /// `**` is right associative and binds tighter than a unary operator on its left,
+    /// so `-2 ** 2` is `-(2 ** 2)` while `2 ** -1` is allowed.
+    fn power(&mut self) -> ParserBoxdResult<Expr> {
+        let expr = self.postfix()?;
+
+        if self.matches(&[TokenType::STAR_STAR]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Box::new(Expr::Binary {
+                left: expr,
+                operator,
+                right,
+            }));
+        }
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> ParserBoxdResult<Expr> {
+        let expr = self.call()?;
+
+        if self.matches(&[TokenType::PLUS_PLUS, TokenType::MINUS_MINUS]) {
+            let operator = self.previous().clone();
+            return Ok(self.increment(expr, operator, false));
         }
+        Ok(expr)
     }
 
     fn call(&mut self) -> ParserBoxdResult<Expr> {
@@ -475,6 +753,16 @@ impl<'a> Parser<'a> {
                     .consume(&TokenType::IDENTIFIER, "Expect property name after '.'")?
                     .clone();
                 expr = Box::new(Expr::Get { object: expr, name })
+            } else if self.matches(&[TokenType::LEFT_BRACKET]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(&TokenType::RIGHT_BRACKET, "Expect ']' after index")?
+                    .clone();
+                expr = Box::new(Expr::Index {
+                    object: expr,
+                    bracket,
+                    index,
+                })
             } else {
                 break;
             }
@@ -489,7 +777,8 @@ impl<'a> Parser<'a> {
         if !self.check(&TokenType::RIGHT_PARAN) {
             loop {
                 if arguments.len() >= 255 {
-                    self.error(ParserError::new(
+                    self.error(&ParserError::new(
+                        "P004",
                         self.peek(),
                         "Function can't have more than 255 arguments",
                     ));
@@ -516,12 +805,14 @@ impl<'a> Parser<'a> {
         use Expr::*;
         use TokenType::*;
 
+        let span = self.peek().span.clone();
+        let litral = |value| Some(Litral { value, span });
         let expr: Option<Expr> = match &self.peek().token_type {
-            FALSE => Some(Litral(LitralValue::False)),
-            TRUE => Some(Litral(LitralValue::True)),
-            NIL => Some(Litral(LitralValue::Nil)),
-            NUMBER { litral } => Some(Litral(LitralValue::NUMBER(litral.clone()))),
-            STRING { litral } => Some(Litral(LitralValue::STRING(litral.clone()))),
+            FALSE => litral(LitralValue::False),
+            TRUE => litral(LitralValue::True),
+            NIL => litral(LitralValue::Nil),
+            NUMBER { litral: value } => litral(LitralValue::NUMBER(*value)),
+            STRING { litral: value } => litral(LitralValue::STRING(Arc::from(value.as_str()))),
             IDENTIFIER => Some(Expr::Variable {
                 name: self.peek().clone(),
                 depth: None,
@@ -547,17 +838,111 @@ impl<'a> Parser<'a> {
                 depth: None,
                 method: self.previous().clone(),
             }))
+        } else if let INTERPOLATION { .. } = self.peek().token_type {
+            self.interpolation()
+        } else if self.matches(&[TokenType::FUN]) {
+            let keyword = self.previous().clone();
+            self.consume(&TokenType::LEFT_PARAN, "Expect '(' after 'fun'")?;
+            let fun = self.function_body(None, "lambda")?;
+            let span = keyword.span.to(&self.previous().span);
+            Ok(Box::new(Expr::Lambda {
+                keyword,
+                fun: Arc::new(fun),
+                span,
+            }))
+        } else if self.matches(&[TokenType::LEFT_BRACKET]) {
+            let left_bracket = self.previous().span.clone();
+            let mut elements = Vec::new();
+            if !self.check(&TokenType::RIGHT_BRACKET) {
+                loop {
+                    elements.push(*self.expression()?);
+                    if !self.matches(&[TokenType::COMMA]) {
+                        break;
+                    }
+                }
+            }
+            let right_bracket = &self
+                .consume(&TokenType::RIGHT_BRACKET, "Expect ']' after list elements")?
+                .span;
+            Ok(Box::new(Expr::List {
+                elements,
+                span: left_bracket.to(right_bracket),
+            }))
+        } else if self.matches(&[TokenType::LEFT_BRACE]) {
+            let left_brace = self.previous().span.clone();
+            let mut entries = Vec::new();
+            if !self.check(&TokenType::RIGHT_BRACE) {
+                loop {
+                    let key = *self.expression()?;
+                    self.consume(&TokenType::COLON, "Expect ':' after map key")?;
+                    let value = *self.expression()?;
+                    entries.push((key, value));
+                    if !self.matches(&[TokenType::COMMA]) {
+                        break;
+                    }
+                }
+            }
+            let brace = self
+                .consume(&TokenType::RIGHT_BRACE, "Expect '}' after map entries")?
+                .clone();
+            let span = left_brace.to(&brace.span);
+            Ok(Box::new(Expr::Map {
+                entries,
+                brace,
+                span,
+            }))
         } else if let LEFT_PARAN = self.peek().token_type {
-            self.advance(); // Important: comsume token & advance
+            let left_paran = self.advance().span.clone(); // Important: comsume token & advance
             let expr = self.expression()?;
-            self.consume(&TokenType::RIGHT_PARAN, "Expect ) after expression")?;
-            Ok(Box::new(Expr::Grouping { expression: expr }))
+            let right_paran = &self
+                .consume(&TokenType::RIGHT_PARAN, "Expect ) after expression")?
+                .span;
+            Ok(Box::new(Expr::Grouping {
+                expression: expr,
+                span: left_paran.to(right_paran),
+            }))
         } else {
             self.advance();
-            Err(ParserError::new(
-                self.previous(),
-                "Unsupported primary token",
-            ))
+            Err(
+                ParserError::new("P005", self.previous(), "Unsupported primary token")
+                    .with_label("expected an expression"),
+            )
+        }
+    }
+
+    /// A string with interpolated expressions, starting at its first `INTERPOLATION` token.
+    fn interpolation(&mut self) -> ParserBoxdResult<Expr> {
+        let start = self.peek().span.clone();
+        let mut parts = Vec::new();
+        loop {
+            let segment = self.advance().clone();
+            let (value, is_last) = match segment.token_type {
+                TokenType::INTERPOLATION { litral } => (litral, false),
+                TokenType::STRING { litral } => (litral, true),
+                _ => {
+                    return Err(ParserError::new(
+                        "P001",
+                        &segment,
+                        "Expect rest of the string after interpolated expression",
+                    )
+                    .with_label(&format!("found '{}'", segment.lexeme)))
+                }
+            };
+            parts.push(Expr::Litral {
+                value: LitralValue::STRING(Arc::from(value)),
+                span: segment.span.clone(),
+            });
+            if is_last {
+                return Ok(Box::new(Expr::Interpolate {
+                    parts,
+                    span: start.to(&segment.span),
+                }));
+            }
+            parts.push(*self.expression()?);
+            self.consume(
+                &TokenType::INTERPOLATION_END,
+                "Expect '}' after interpolated expression",
+            )?;
         }
     }
 
@@ -571,7 +956,8 @@ impl<'a> Parser<'a> {
             }
 
             match self.peek().token_type {
-                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN => return,
+                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN | BREAK | CONTINUE
+                | THROW | TRY => return,
                 _ => {
                     self.advance();
                     continue;
@@ -583,32 +969,112 @@ impl<'a> Parser<'a> {
 
 #[derive(Debug)]
 pub struct ParserError {
+    pub code: &'static str,
     pub token_type: TokenType,
-    pub line: usize,
+    pub lexeme: String,
+    pub span: Span,
     pub message: String,
+    pub label: Option<String>,
 }
 
 impl ParserError {
-    fn new(token: &Token, message: &str) -> ParserError {
-        ParserError {
+    fn new(code: &'static str, token: &Token, message: &str) -> Box<ParserError> {
+        Box::new(ParserError {
+            code,
             token_type: token.token_type.clone(),
-            line: token.line,
+            lexeme: token.lexeme.clone(),
+            span: token.span.clone(),
             message: String::from(message),
-        }
+            label: None,
+        })
+    }
+
+    fn with_label(mut self: Box<Self>, label: &str) -> Box<ParserError> {
+        self.label = Some(String::from(label));
+        self
     }
 }
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Parser error: line {} at [{:?}] {}",
-            self.line, self.token_type, self.message
-        )
+        if let TokenType::EOF = self.token_type {
+            write!(f, "Parser error: {} at end: {}", self.span, self.message)
+        } else {
+            write!(
+                f,
+                "Parser error: {} at '{}': {}",
+                self.span, self.lexeme, self.message
+            )
+        }
     }
 }
 
 impl Error for ParserError {}
 
-pub type ParserResult<T> = Result<T, ParserError>;
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Self {
+        let diagnostic = Diagnostic::error(Phase::Parse, err.code, &err.message, err.span.clone());
+        match &err.label {
+            Some(label) => diagnostic.with_label(label),
+            None => diagnostic,
+        }
+    }
+}
+
+pub type ParserResult<T> = Result<T, Box<ParserError>>;
 pub type ParserBoxdResult<T> = ParserResult<Box<T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::printer::AstPrinter;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> CompileResult<Vec<Stmt>> {
+        let mut scanner = Scanner::new(String::from(source));
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        Parser::new(tokens).parse()
+    }
+
+    /// Code, message and column of the first error reported for `source`.
+    fn first_error(source: &str) -> (&'static str, String, usize) {
+        let diagnostic = parse(source)
+            .expect_err("source should not parse")
+            .remove(0);
+        (diagnostic.code, diagnostic.message, diagnostic.span.col)
+    }
+
+    #[test]
+    fn interpolation_parts() {
+        let stmts = parse("print \"a${x}b${y + 1}c\";").expect("source should parse");
+        assert_eq!(
+            AstPrinter::print(&stmts),
+            "(print (interpolate \"a\" x \"b\" (+ y 1) \"c\"))"
+        );
+    }
+
+    #[test]
+    fn interpolation_without_operand_is_reported_at_the_closing_brace() {
+        let unsupported = String::from("Unsupported primary token");
+        assert_eq!(
+            first_error("\"${1 + }\";"),
+            ("P005", unsupported.clone(), 8)
+        );
+        assert_eq!(
+            first_error("print \"${\"x\" +}\" \"y\";"),
+            ("P005", unsupported, 15)
+        );
+    }
+
+    #[test]
+    fn interpolation_with_trailing_tokens_expects_closing_brace() {
+        assert_eq!(
+            first_error("\"${1 2}\";"),
+            (
+                "P001",
+                String::from("Expect '}' after interpolated expression"),
+                6
+            )
+        );
+    }
+}
@@ -1,15 +1,28 @@
-use crate::{ast::Fun, token::Token};
+use crate::{
+    ast::{walk_expr_mut, walk_stmt_mut, Fun, VisitorMut},
+    error::{CompileResult, Diagnostic, Phase},
+    token::{Span, Token},
+};
 
 use super::ast::{Expr, Stmt};
 use std::collections::HashMap;
 use std::error;
 use std::fmt;
+use std::sync::Arc;
 
 pub struct Resolver {
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Binding>>,
     current_function: Option<FunctionType>,
     current_class: Option<ClassType>,
-    num_of_resolver_errs: usize,
+    /// Number of loops enclosing the current statement, within the current function.
+    loop_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Resolver {
@@ -18,19 +31,114 @@ impl Resolver {
             scopes: Vec::new(),
             current_function: None,
             current_class: None,
-            num_of_resolver_errs: 0,
+            loop_depth: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Resolve the variables in `stmts`, on failure returns every error found.
+    pub fn resolve_stmts(&mut self, stmts: &mut [Stmt]) -> CompileResult<()> {
+        self.resolve_body(stmts);
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    fn resolve_body(&mut self, stmts: &mut [Stmt]) {
+        stmts.iter_mut().for_each(|stmt| self.visit_stmt_mut(stmt));
+    }
+
+    fn resolve_function(&mut self, fun: &mut Fun, fun_type: FunctionType) {
+        let enclosing_function = self.current_function.take();
+        self.current_function = Some(fun_type);
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+
+        self.begin_scope();
+        fun.params.iter().for_each(|param| {
+            self.declare(param);
+            self.define(param);
+        });
+        self.resolve_body(fun.body.as_mut());
+        self.end_scope();
+
+        self.loop_depth = enclosing_loop_depth;
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_local_depth(&self, name: &Token) -> Option<usize> {
+        let result = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .try_for_each(|(i, scope)| {
+                if scope.contains_key(&name.lexeme) {
+                    Err(i)
+                } else {
+                    Ok(())
+                }
+            });
+        result.err()
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::<String, Binding>::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if self.scopes.is_empty() {
+            return;
+        }
+        let scope = self.scopes.last_mut().unwrap();
+
+        if let Some(existing) = scope.get(&name.lexeme) {
+            let declared_at = existing.declared_at.clone();
+            self.error(
+                &ResolverError::new(
+                    "R008",
+                    name,
+                    "Already a variable with this name in this scope",
+                )
+                .with_label("redeclared here")
+                .with_related(declared_at, "variable first declared here"),
+            )
+        } else {
+            scope.insert(
+                name.lexeme.clone(),
+                Binding {
+                    is_defined: false,
+                    declared_at: name.span.clone(),
+                },
+            );
         }
     }
 
-    pub fn get_num_of_resolver_errs(&self) -> usize {
-        self.num_of_resolver_errs
+    fn define(&mut self, name: &Token) {
+        if self.scopes.is_empty() {
+            return;
+        }
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .entry(name.lexeme.clone())
+            .and_modify(|binding| binding.is_defined = true)
+            .or_insert_with(|| Binding::defined(&name.span));
     }
 
-    pub fn resolve_stmts(&mut self, stmts: &mut Vec<Stmt>) {
-        stmts.iter_mut().for_each(|stmt| self.resolve_stmt(stmt));
+    fn error(&mut self, err: &ResolverError) {
+        self.diagnostics.push(err.into());
     }
+}
 
-    fn resolve_stmt(&mut self, stmt: &mut Stmt) {
+impl VisitorMut for Resolver {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
         match stmt {
             Stmt::Class {
                 name,
@@ -43,44 +151,47 @@ impl Resolver {
                 self.declare(name);
                 self.define(name);
 
-                super_class.as_ref().map(|super_class| {
-                    if let Expr::Variable {
-                        name: super_class_name,
-                        ..
-                    } = super_class
-                    {
-                        if super_class_name.lexeme.eq(&name.lexeme) {
-                            self.error(&ResolverError::new(
-                                super_class_name,
-                                "A class can't interit from itself",
-                            ))
-                        }
+                if let Some(Expr::Variable {
+                    name: super_class_name,
+                    ..
+                }) = super_class.as_ref()
+                {
+                    if super_class_name.lexeme.eq(&name.lexeme) {
+                        self.error(&ResolverError::new(
+                            "R001",
+                            super_class_name,
+                            "A class can't interit from itself",
+                        ))
                     }
-                });
+                }
 
                 if let Some(super_class) = super_class {
                     self.current_class = Some(ClassType::SubClass);
-                    self.resolve_expr(super_class);
+                    self.visit_expr_mut(super_class);
 
                     self.begin_scope(); // 'super' scope
                     self.scopes
                         .last_mut()
                         .unwrap()
-                        .insert(String::from("super"), true);
+                        .insert(String::from("super"), Binding::defined(&name.span));
                 }
 
                 self.begin_scope(); // 'this' scope
                 self.scopes
                     .last_mut()
                     .unwrap()
-                    .insert(String::from("this"), true);
+                    .insert(String::from("this"), Binding::defined(&name.span));
 
                 methods.iter_mut().for_each(|method| {
                     let mut declaration = FunctionType::Method;
-                    if method.name.lexeme == "init" {
+                    if method
+                        .name
+                        .as_ref()
+                        .is_some_and(|name| name.lexeme == "init")
+                    {
                         declaration = FunctionType::Initializer;
                     }
-                    self.resolve_function(method, declaration);
+                    self.resolve_function(Arc::make_mut(method), declaration);
                 });
 
                 self.end_scope(); // end of 'this' scope
@@ -94,73 +205,98 @@ impl Resolver {
             Stmt::Var { name, expression } => {
                 self.declare(name);
                 if let Some(expression) = expression {
-                    self.resolve_expr(expression)
+                    self.visit_expr_mut(expression)
                 }
                 self.define(name)
             }
             Stmt::Function(fun) => {
-                self.declare(&fun.name);
-                self.define(&fun.name);
-                self.resolve_function(fun, FunctionType::Function);
+                if let Some(name) = &fun.name {
+                    self.declare(name);
+                    self.define(name);
+                }
+                self.resolve_function(Arc::make_mut(fun), FunctionType::Function);
             }
             Stmt::Block { statements } => {
                 self.begin_scope();
-                self.resolve_stmts(statements);
+                self.resolve_body(statements);
                 self.end_scope();
             }
-
-            Stmt::PrintStmt { expression } => self.resolve_expr(expression),
-            Stmt::ExpressionStmt { expression } => self.resolve_expr(expression),
-            Stmt::IfStmt {
-                condition,
-                then_branch,
-                else_branch,
-            } => {
-                self.resolve_expr(condition);
-                self.resolve_stmt(then_branch);
-                else_branch.as_mut().and_then(|else_branch| {
-                    self.resolve_stmt(else_branch);
-                    Some(())
-                });
-            }
-            Stmt::WhileStmt { condition, body } => {
-                self.resolve_expr(condition);
-                self.resolve_stmt(body.as_mut());
-            }
             Stmt::Return { keyword, value } => {
                 if let Some(fun_type) = self.current_function {
-                    value.as_mut().and_then(|value| {
+                    if let Some(value) = value {
                         if fun_type == FunctionType::Initializer {
                             self.error(&ResolverError::new(
+                                "R002",
                                 keyword,
                                 "Can't return a value from constructor",
                             ))
                         } else {
-                            self.resolve_expr(value);
+                            self.visit_expr_mut(value);
                         }
-
-                        Some(())
-                    });
+                    }
                 } else {
                     self.error(&ResolverError::new(
-                        &keyword,
+                        "R003",
+                        keyword,
                         "Return statement allowed only inside a function or method",
                     ))
                 }
             }
+            Stmt::WhileStmt { .. } => {
+                self.loop_depth += 1;
+                walk_stmt_mut(self, stmt);
+                self.loop_depth -= 1;
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                self.begin_scope();
+                self.resolve_body(body);
+                self.end_scope();
+                if let Some(catch) = catch {
+                    self.begin_scope();
+                    self.declare(&catch.name);
+                    self.define(&catch.name);
+                    self.resolve_body(&mut catch.body);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.resolve_body(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::Break { keyword } | Stmt::Continue { keyword } => {
+                if self.loop_depth == 0 {
+                    self.error(&ResolverError::new(
+                        "R009",
+                        keyword,
+                        &format!("Can't use '{}' outside of a loop", keyword.lexeme),
+                    ))
+                }
+            }
+            _ => walk_stmt_mut(self, stmt),
         }
     }
 
-    fn resolve_expr(&mut self, expr: &mut Expr) {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
         match expr {
             Expr::Variable { name, depth } => {
-                if !self.scopes.is_empty() {
-                    if let Some(false) = self.scopes.last().unwrap().get(&name.lexeme) {
-                        self.error(&ResolverError::new(
+                if let Some(Binding {
+                    is_defined: false, ..
+                }) = self.scopes.last().and_then(|scope| scope.get(&name.lexeme))
+                {
+                    self.error(
+                        &ResolverError::new(
+                            "R004",
                             name,
                             "Can't read local variable in its own initializer",
-                        ))
-                    }
+                        )
+                        .with_label("read before the initializer is complete"),
+                    )
                 }
                 *depth = self.resolve_local_depth(name)
             }
@@ -170,147 +306,53 @@ impl Resolver {
                 depth,
             } => match self.current_class {
                 None => self.error(&ResolverError::new(
-                    &keyword,
+                    "R005",
+                    keyword,
                     "Can't use 'super' outside a class",
                 )),
                 Some(ClassType::Class) => self.error(&ResolverError::new(
-                    &keyword,
+                    "R006",
+                    keyword,
                     "Can't use 'super' keyword on a class without a super class",
                 )),
-                Some(ClassType::SubClass) => *depth = self.resolve_local_depth(&keyword),
+                Some(ClassType::SubClass) => *depth = self.resolve_local_depth(keyword),
             },
             Expr::This { keyword, depth } => {
-                if let Some(_) = self.current_class {
+                if self.current_class.is_some() {
                     *depth = self.resolve_local_depth(keyword);
                 } else {
                     self.error(&ResolverError::new(
+                        "R007",
                         keyword,
                         "Can't use 'this' outside of a class",
                     ));
                 }
             }
             Expr::Assign { name, value, depth } => {
-                self.resolve_expr(value);
+                self.visit_expr_mut(value);
                 *depth = self.resolve_local_depth(name)
             }
-            Expr::Litral(_) => {}
-            Expr::Unary { operator: _, right } => {
-                self.resolve_expr(right);
-            }
-            Expr::Binary {
-                left,
-                operator: _,
-                right,
-            } => {
-                self.resolve_expr(left);
-                self.resolve_expr(right);
-            }
-            Expr::Logical {
-                left,
-                operator: _,
-                right,
-            } => {
-                self.resolve_expr(left);
-                self.resolve_expr(right);
-            }
-            Expr::Grouping { expression } => {
-                self.resolve_expr(expression);
-            }
-            Expr::Call {
-                callee,
-                paran: _,
-                arguments,
-            } => {
-                self.resolve_expr(callee);
-                arguments
-                    .iter_mut()
-                    .for_each(|argument| self.resolve_expr(argument));
-            }
-            Expr::Get { object, name: _ } => self.resolve_expr(object),
-            Expr::Set {
-                object,
-                name: _,
-                value,
-            } => {
-                self.resolve_expr(object);
-                self.resolve_expr(value);
+            Expr::Lambda { fun, .. } => {
+                self.resolve_function(Arc::make_mut(fun), FunctionType::Function)
             }
+            _ => walk_expr_mut(self, expr),
         }
     }
+}
 
-    fn resolve_function(&mut self, fun: &mut Fun, fun_type: FunctionType) {
-        let enclosing_function = self.current_function.take();
-        self.current_function = Some(fun_type);
-
-        self.begin_scope();
-        fun.params.iter().for_each(|param| {
-            self.declare(param);
-            self.define(param);
-        });
-        self.resolve_stmts(fun.body.as_mut());
-        self.end_scope();
-
-        self.current_function = enclosing_function;
-    }
-
-    fn resolve_local_depth(&self, name: &Token) -> Option<usize> {
-        let result = self
-            .scopes
-            .iter()
-            .rev()
-            .enumerate()
-            .try_for_each(|(i, scope)| {
-                if scope.contains_key(&name.lexeme) {
-                    Err(i)
-                } else {
-                    Ok(())
-                }
-            });
-        if let Err(depth) = result {
-            Some(depth)
-        } else {
-            None
-        }
-    }
-
-    fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::<String, bool>::new());
-    }
-
-    fn end_scope(&mut self) {
-        self.scopes.pop();
-    }
-
-    fn declare(&mut self, name: &Token) {
-        if self.scopes.is_empty() {
-            return;
-        }
-        let scope = self.scopes.last_mut().unwrap();
-
-        if !scope.contains_key(&name.lexeme) {
-            scope.insert(name.lexeme.clone(), false);
-        } else {
-            self.error(&ResolverError::new(
-                name,
-                "Already a variable with this name in this scope",
-            ))
-        }
-    }
+/// State of a local name in a scope.
+struct Binding {
+    /// false until the initializer of the variable has been resolved.
+    is_defined: bool,
+    declared_at: Span,
+}
 
-    fn define(&mut self, name: &Token) {
-        if self.scopes.is_empty() {
-            return;
+impl Binding {
+    fn defined(declared_at: &Span) -> Binding {
+        Binding {
+            is_defined: true,
+            declared_at: declared_at.clone(),
         }
-
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert(name.lexeme.clone(), true);
-    }
-
-    fn error(&mut self, err: &ResolverError) {
-        self.num_of_resolver_errs += 1;
-        crate::error::error_at_compiler(err)
     }
 }
 
@@ -328,27 +370,62 @@ enum ClassType {
 
 #[derive(Debug)]
 struct ResolverError {
+    code: &'static str,
     token: Token,
     message: String,
+    label: Option<&'static str>,
+    related: Option<(Span, &'static str)>,
 }
 
 impl ResolverError {
-    fn new(token: &Token, message: &str) -> ResolverError {
+    fn new(code: &'static str, token: &Token, message: &str) -> ResolverError {
         ResolverError {
+            code,
             token: token.clone(),
             message: String::from(message),
+            label: None,
+            related: None,
         }
     }
+
+    fn with_label(mut self, label: &'static str) -> ResolverError {
+        self.label = Some(label);
+        self
+    }
+
+    /// Point at another location relevant to the error.
+    fn with_related(mut self, span: Span, message: &'static str) -> ResolverError {
+        self.related = Some((span, message));
+        self
+    }
 }
 
-impl<'a> fmt::Display for ResolverError {
+impl fmt::Display for ResolverError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Resolver error: line {} at [{:?}] {}",
-            self.token.line, self.token.token_type, self.message
+            "Resolver error: {} at [{:?}] {}",
+            self.token.span, self.token.token_type, self.message
         )
     }
 }
 
-impl<'a> error::Error for ResolverError {}
+impl error::Error for ResolverError {}
+
+impl From<&ResolverError> for Diagnostic {
+    fn from(err: &ResolverError) -> Self {
+        let mut diagnostic = Diagnostic::error(
+            Phase::Resolve,
+            err.code,
+            &err.message,
+            err.token.span.clone(),
+        );
+        if let Some(label) = err.label {
+            diagnostic = diagnostic.with_label(label);
+        }
+        if let Some((span, message)) = &err.related {
+            diagnostic = diagnostic.with_secondary_label(span.clone(), message);
+        }
+        diagnostic
+    }
+}
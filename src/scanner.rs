@@ -1,50 +1,147 @@
-use core::str;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::{
-    error::error,
-    token::{Token, TokenType},
+    error::{CompileResult, Diagnostic, Phase},
+    token::{Span, Token, TokenType},
 };
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Positions are grapheme indices into `source_graphemes`.
+#[derive(Clone)]
 struct ScanPosition {
     start: usize,
     current: usize,
     line: usize,
+    /// Index of the first grapheme of the current line.
+    line_start: usize,
+    /// Line and column where the token being scanned starts.
+    start_line: usize,
+    start_col: usize,
 }
 
 pub struct Scanner {
     source_graphemes: Vec<String>,
+    /// Byte offset of every grapheme, plus the source length at the end.
+    byte_offsets: Vec<usize>,
+    file: Option<Arc<str>>,
     pos: ScanPosition,
     tokens: Vec<Token>,
+    diagnostics: Vec<Diagnostic>,
+    /// One entry per `${` whose closing brace hasn't been seen yet, innermost last.
+    interpolations: Vec<Interpolation>,
+    /// Line on which the last token ended.
+    last_token_line: usize,
     keywords: HashMap<&'static str, TokenType>,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Scanner {
+        let source_graphemes: Vec<String> = source.graphemes(true).map(String::from).collect();
+        let mut byte_offsets = Vec::with_capacity(source_graphemes.len() + 1);
+        let mut offset = 0;
+        for grapheme in &source_graphemes {
+            byte_offsets.push(offset);
+            offset += grapheme.len();
+        }
+        byte_offsets.push(offset);
+
         Scanner {
-            source_graphemes: source
-                .graphemes(true)
-                .map(|str| String::from(str))
-                .collect(),
+            source_graphemes,
+            byte_offsets,
+            file: None,
             pos: ScanPosition {
                 start: 0,
                 current: 0,
                 line: 1,
+                line_start: 0,
+                start_line: 1,
+                start_col: 1,
             },
             tokens: Vec::new(),
+            diagnostics: Vec::new(),
+            interpolations: Vec::new(),
+            last_token_line: 0,
             keywords: KEYWORDS(),
         }
     }
 
-    pub fn scan_tokens(&mut self) -> &Vec<Token> {
+    /// instantiate scanner for the contents of `file`, spans will carry the file name.
+    pub fn new_with_file(source: String, file: &str) -> Scanner {
+        let mut scanner = Scanner::new(source);
+        scanner.file = Some(Arc::from(file));
+        scanner
+    }
+
+    /// Scan the whole source, on failure returns every problem found.
+    pub fn scan_tokens(&mut self) -> CompileResult<&Vec<Token>> {
         while !self.is_at_end() {
-            self.pos.start = self.pos.current;
+            self.begin_token();
             self.scan_token()
         }
 
+        std::mem::take(&mut self.interpolations)
+            .into_iter()
+            .for_each(|interpolation| {
+                self.error_at(
+                    interpolation.start,
+                    "S006",
+                    "Unterminated string interpolation.",
+                    "interpolation starts here",
+                )
+            });
+
+        self.begin_token();
         self.add_token(TokenType::EOF);
-        &self.tokens
+        if self.diagnostics.is_empty() {
+            Ok(&self.tokens)
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    /// Report an error covering the token being scanned.
+    fn error(&mut self, code: &'static str, message: &str, label: &str) {
+        self.error_at(self.current_span(), code, message, label);
+    }
+
+    fn error_at(&mut self, span: Span, code: &'static str, message: &str, label: &str) {
+        self.diagnostics
+            .push(Diagnostic::error(Phase::Scan, code, message, span).with_label(label));
+    }
+
+    fn begin_token(&mut self) {
+        self.pos.start = self.pos.current;
+        self.pos.start_line = self.pos.line;
+        self.pos.start_col = self.pos.start - self.pos.line_start + 1;
+    }
+
+    /// Call after consuming a new line grapheme.
+    fn new_line(&mut self) {
+        self.pos.line += 1;
+        self.pos.line_start = self.pos.current;
+    }
+
+    fn span(&self, start: usize, end: usize, line: usize, col: usize) -> Span {
+        Span {
+            file: self.file.clone(),
+            start: self.byte_offsets[start],
+            end: self.byte_offsets[end],
+            line,
+            col,
+        }
+    }
+
+    /// Span of the token being scanned.
+    fn current_span(&self) -> Span {
+        self.span(
+            self.pos.start,
+            self.pos.current,
+            self.pos.start_line,
+            self.pos.start_col,
+        )
     }
 
     fn is_at_end(&self) -> bool {
@@ -56,14 +153,82 @@ impl Scanner {
         match c.as_str() {
             "(" => self.add_token(TokenType::LEFT_PARAN),
             ")" => self.add_token(TokenType::RIGHT_PARAN),
-            "{" => self.add_token(TokenType::LEFT_BRACE),
-            "}" => self.add_token(TokenType::RIGHT_BRACE),
+            "{" => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.open_braces += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE)
+            }
+            "}" => match self.interpolations.last_mut() {
+                Some(interpolation) if interpolation.open_braces == 0 => {
+                    // End of the interpolated expression, the string continues.
+                    self.interpolations.pop();
+                    self.add_token(TokenType::INTERPOLATION_END);
+                    self.begin_token();
+                    self.string_litral();
+                }
+                Some(interpolation) => {
+                    interpolation.open_braces -= 1;
+                    self.add_token(TokenType::RIGHT_BRACE)
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE),
+            },
+            "[" => self.add_token(TokenType::LEFT_BRACKET),
+            "]" => self.add_token(TokenType::RIGHT_BRACKET),
             "," => self.add_token(TokenType::COMMA),
+            ":" => self.add_token(TokenType::COLON),
             "." => self.add_token(TokenType::DOT),
-            "-" => self.add_token(TokenType::MINUS),
-            "+" => self.add_token(TokenType::PLUS),
+            "-" => {
+                let token = if self.advance_if_matched("-") {
+                    TokenType::MINUS_MINUS
+                } else if self.advance_if_matched("=") {
+                    TokenType::MINUS_EQUAL
+                } else {
+                    TokenType::MINUS
+                };
+                self.add_token(token)
+            }
+            "+" => {
+                let token = if self.advance_if_matched("+") {
+                    TokenType::PLUS_PLUS
+                } else if self.advance_if_matched("=") {
+                    TokenType::PLUS_EQUAL
+                } else {
+                    TokenType::PLUS
+                };
+                self.add_token(token)
+            }
             ";" => self.add_token(TokenType::SEMICOLON),
-            "*" => self.add_token(TokenType::STAR),
+            "%" => {
+                let token = if self.advance_if_matched("=") {
+                    TokenType::PERCENT_EQUAL
+                } else {
+                    TokenType::PERCENT
+                };
+                self.add_token(token)
+            }
+            "&" => self.add_token(TokenType::AMPERSAND),
+            "|" => self.add_token(TokenType::PIPE),
+            "^" => self.add_token(TokenType::CARET),
+            "~" => self.add_token(TokenType::TILDE),
+            "?" => {
+                let token = if self.advance_if_matched("?") {
+                    TokenType::QUESTION_QUESTION
+                } else {
+                    TokenType::QUESTION
+                };
+                self.add_token(token)
+            }
+            "*" => {
+                let token = if self.advance_if_matched("*") {
+                    TokenType::STAR_STAR
+                } else if self.advance_if_matched("=") {
+                    TokenType::STAR_EQUAL
+                } else {
+                    TokenType::STAR
+                };
+                self.add_token(token)
+            }
             "!" => {
                 let token = if self.advance_if_matched("=") {
                     TokenType::BANG_EQUAL
@@ -83,6 +248,8 @@ impl Scanner {
             "<" => {
                 let token = if self.advance_if_matched("=") {
                     TokenType::LESS_EQUAL
+                } else if self.advance_if_matched("<") {
+                    TokenType::LESS_LESS
                 } else {
                     TokenType::LESS
                 };
@@ -91,6 +258,8 @@ impl Scanner {
             ">" => {
                 let token = if self.advance_if_matched("=") {
                     TokenType::GREATER_EQUAL
+                } else if self.advance_if_matched(">") {
+                    TokenType::GREATER_GREATER
                 } else {
                     TokenType::GREATER
                 };
@@ -100,7 +269,7 @@ impl Scanner {
                 self.handle_slash();
             }
             " " | "\r" | "\t" => (), // Ignoring whitespaces.
-            "\n" => self.pos.line += 1,
+            "\n" | "\r\n" => self.new_line(),
             "\"" => self.string_litral(),
 
             c => {
@@ -109,7 +278,11 @@ impl Scanner {
                 } else if Self::is_alpha(c) {
                     self.identifier();
                 } else {
-                    error(self.pos.line, &format!("Unexpected charactor {}", c));
+                    self.error(
+                        "S001",
+                        &format!("Unexpected charactor {}", c),
+                        "not valid in Lox source",
+                    );
                 }
             }
         }
@@ -122,13 +295,11 @@ impl Scanner {
     }
 
     fn advance_if_matched(&mut self, expected: &str) -> bool {
-        if self.is_at_end() {
-            return false;
-        } else if self.source_graphemes[self.pos.current] != expected {
-            return false;
+        if self.is_at_end() || self.source_graphemes[self.pos.current] != expected {
+            false
         } else {
             self.pos.current += 1;
-            return true;
+            true
         }
     }
 
@@ -150,14 +321,93 @@ impl Scanner {
 
     fn add_token(&mut self, token_type: TokenType) {
         let text = self.source_graphemes[self.pos.start..self.pos.current].join("");
-        self.tokens
-            .push(Token::new(token_type, text, self.pos.line))
+        let span = self.current_span();
+        self.last_token_line = self.pos.line;
+        self.tokens.push(Token::new(token_type, text, span))
+    }
+
+    /// Call after consuming the first '/' of a `//`. It is integer division when it
+    /// follows an operand on the same line and the rest of the line scans cleanly up to
+    /// the end of the expression: a ';', or a closing bracket opened before the `//`.
+    /// Anywhere else it starts a comment, eg. `var x = a // note` or `init() // set up`.
+    fn is_integer_division(&mut self) -> bool {
+        let follows_operand = self.last_token_line == self.pos.start_line
+            && self.tokens.last().is_some_and(|previous| {
+                matches!(
+                    previous.token_type,
+                    TokenType::NUMBER { .. }
+                        | TokenType::STRING { .. }
+                        | TokenType::IDENTIFIER
+                        | TokenType::RIGHT_PARAN
+                        | TokenType::RIGHT_BRACKET
+                        | TokenType::RIGHT_BRACE
+                        | TokenType::PLUS_PLUS
+                        | TokenType::MINUS_MINUS
+                        | TokenType::TRUE
+                        | TokenType::FALSE
+                        | TokenType::NIL
+                        | TokenType::THIS
+                )
+            });
+        if !follows_operand {
+            return false;
+        }
+
+        // Scan the rest of the line ahead, then put the scanner back where it was.
+        let (pos, interpolations) = (self.pos.clone(), self.interpolations.clone());
+        let (token_count, diagnostic_count) = (self.tokens.len(), self.diagnostics.len());
+        let last_token_line = self.last_token_line;
+        let line = self.pos.line;
+        self.advance();
+        let mut open_brackets = 0;
+        let mut ends_expression = false;
+        let mut checked = token_count;
+        while !ends_expression
+            && !self.is_at_end()
+            && self.pos.line == line
+            && !Self::is_new_line(self.peek())
+        {
+            self.begin_token();
+            self.scan_token();
+            for token in &self.tokens[checked..] {
+                match token.token_type {
+                    TokenType::LEFT_PARAN
+                    | TokenType::LEFT_BRACKET
+                    | TokenType::LEFT_BRACE
+                    | TokenType::INTERPOLATION { .. } => open_brackets += 1,
+                    TokenType::RIGHT_PARAN
+                    | TokenType::RIGHT_BRACKET
+                    | TokenType::RIGHT_BRACE
+                    | TokenType::INTERPOLATION_END => {
+                        if open_brackets == 0 {
+                            ends_expression = true;
+                        } else {
+                            open_brackets -= 1;
+                        }
+                    }
+                    TokenType::SEMICOLON if open_brackets == 0 => ends_expression = true,
+                    _ => {}
+                }
+            }
+            checked = self.tokens.len();
+        }
+        let scanned_cleanly = self.diagnostics.len() == diagnostic_count;
+
+        self.pos = pos;
+        self.interpolations = interpolations;
+        self.last_token_line = last_token_line;
+        self.tokens.truncate(token_count);
+        self.diagnostics.truncate(diagnostic_count);
+        ends_expression && scanned_cleanly
     }
 
     fn handle_slash(&mut self) {
-        if self.advance_if_matched("/") {
+        if self.peek() == "/" && self.is_integer_division() {
+            self.advance();
+            self.add_token(TokenType::SLASH_SLASH)
+        } else if self.advance_if_matched("/") {
             // single line comment
-            while self.peek() != "\n" && !self.is_at_end() {
+            while !Self::is_new_line(self.peek()) && !self.is_at_end() {
                 self.advance();
             }
         } else if self.advance_if_matched("*") {
@@ -165,9 +415,9 @@ impl Scanner {
             let mut comment_terminated = false;
             while !self.is_at_end() {
                 match self.peek() {
-                    "\n" => {
-                        self.pos.line += 1;
+                    "\n" | "\r\n" => {
                         self.advance();
+                        self.new_line();
                     }
                     "*" => {
                         if self.peek_next() != "/" {
@@ -186,91 +436,276 @@ impl Scanner {
                 }
             }
             if !comment_terminated {
-                error(self.pos.line, "Multi-line comment did't terminate!.");
+                self.error(
+                    "S002",
+                    "Multi-line comment did't terminate!.",
+                    "comment starts here",
+                );
             }
+        } else if self.advance_if_matched("=") {
+            self.add_token(TokenType::SLASH_EQUAL)
         } else {
             self.add_token(TokenType::SLASH)
         }
     }
 
+    /// Scans the rest of a string after its opening quote, or after the '}' closing
+    /// an interpolated expression.
     fn string_litral(&mut self) {
+        let mut value = String::new();
         while self.peek() != "\"" && !self.is_at_end() {
-            if self.peek() == "\n" {
-                self.pos.line += 1;
+            if self.peek() == "$" && self.peek_next() == "{" {
+                let (line, col) = (self.pos.line, self.pos.current - self.pos.line_start + 1);
+                let start = self.span(self.pos.current, self.pos.current + 2, line, col);
+                self.advance();
+                self.advance();
+                self.add_token(TokenType::INTERPOLATION { litral: value });
+                self.interpolations.push(Interpolation {
+                    open_braces: 0,
+                    start,
+                });
+                return;
+            }
+            let c = self.advance().to_string();
+            if c == "\\" {
+                self.escape_sequence(&mut value);
+            } else {
+                if Self::is_new_line(&c) {
+                    self.new_line();
+                }
+                value.push_str(&c);
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            error(self.pos.line, "Unterminated string.")
-        } else {
-            self.advance(); // The closing ".
+            self.error("S003", "Unterminated string.", "string starts here");
+            return;
         }
+        self.advance(); // The closing ".
 
-        let value = self.source_graphemes[self.pos.start + 1..self.pos.current - 1].join("");
         self.add_token(TokenType::STRING { litral: value })
     }
 
+    /// Call after consuming the '\' of an escape sequence, appends the escaped charactor
+    /// to `value`. Errors point at the escape sequence alone.
+    fn escape_sequence(&mut self, value: &mut String) {
+        let start = self.pos.current - 1;
+        let (line, col) = (self.pos.line, start - self.pos.line_start + 1);
+        if self.is_at_end() {
+            return; // reported as an unterminated string.
+        }
+
+        let c = self.advance().to_string();
+        match c.as_str() {
+            "n" => value.push('\n'),
+            "t" => value.push('\t'),
+            "r" => value.push('\r'),
+            "0" => value.push('\0'),
+            "\"" => value.push('"'),
+            "\\" => value.push('\\'),
+            "$" => value.push('$'),
+            "u" => match self.unicode_escape() {
+                Some(c) => value.push(c),
+                None => self.error_at(
+                    self.span(start, self.pos.current, line, col),
+                    "S005",
+                    "Malformed unicode escape",
+                    "expected \\u{...} with 1 to 6 hex digits of a unicode scalar value",
+                ),
+            },
+            c => {
+                if Self::is_new_line(c) {
+                    self.new_line();
+                }
+                self.error_at(
+                    self.span(start, self.pos.current, line, col),
+                    "S004",
+                    &format!("Unknown escape sequence '\\{}'", c.escape_debug()),
+                    "unknown escape",
+                );
+            }
+        }
+    }
+
+    /// The `{1F600}` part of a `\u{1F600}` escape, consumes as much of it as is well formed.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.advance_if_matched("{") {
+            return None;
+        }
+        let mut digits = String::new();
+        while self.peek().len() == 1 && self.peek().as_bytes()[0].is_ascii_hexdigit() {
+            digits.push_str(self.advance());
+        }
+        if !self.advance_if_matched("}") || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    /// Number literals are decimal with an optional fraction and exponent (`6.02e23`),
+    /// hexadecimal (`0xFF`) or binary (`0b1010`). Digits can be grouped with '_'.
     fn number(&mut self) {
-        while Self::is_digit(self.peek()) {
-            self.advance();
+        let first = self.source_graphemes[self.pos.start].clone();
+        let radix_prefix = self.peek().to_ascii_lowercase();
+        if first == "0" && (radix_prefix == "x" || radix_prefix == "b") {
+            self.advance(); // consume "x" or "b"
+            let (radix, name): (u32, &str) = if radix_prefix == "x" {
+                (16, "hex")
+            } else {
+                (2, "binary")
+            };
+            let digits = self.digits(|c| c.len() == 1 && c.as_bytes()[0].is_ascii_hexdigit());
+            let value = digits
+                .chars()
+                .filter(|c| *c != '_')
+                .map(|c| c.to_digit(radix))
+                .try_fold(0.0, |value, digit| {
+                    Some(value * radix as f64 + digit? as f64)
+                });
+            match value {
+                Some(value) if Self::is_digit_group(&digits) => self.number_token(value),
+                Some(_) if digits.chars().any(|c| c != '_') => self.error(
+                    "S007",
+                    "Malformed number literal.",
+                    "'_' can only be used between digits",
+                ),
+                _ => self.error(
+                    "S007",
+                    "Malformed number literal.",
+                    &format!("expected {} digits after '0{}'", name, radix_prefix),
+                ),
+            }
+            return;
         }
 
+        let mut digits = first + &self.digits(Self::is_digit);
+        let mut label = "'_' can only be used between digits";
         if self.peek() == "." && Self::is_digit(self.peek_next()) {
             self.advance(); // consume "."
-            while Self::is_digit(self.peek()) {
-                self.advance();
+            digits = format!("{}.{}", digits, self.digits(Self::is_digit));
+        }
+        if self.peek() == "e" || self.peek() == "E" {
+            self.advance(); // consume "e"
+            let sign = if self.advance_if_matched("-") {
+                "-"
+            } else {
+                self.advance_if_matched("+");
+                ""
+            };
+            let exponent = self.digits(Self::is_digit);
+            if exponent.is_empty() {
+                label = "expected digits in the exponent";
             }
+            digits = format!("{}e{}{}", digits, sign, exponent);
+        }
+
+        let value = if digits
+            .split(['.', 'e'])
+            .all(|group| Self::is_digit_group(group.trim_start_matches('-')))
+        {
+            digits.replace('_', "").parse::<f64>().ok()
+        } else {
+            None
+        };
+        match value {
+            Some(value) => self.number_token(value),
+            None => self.error("S007", "Malformed number literal.", label),
         }
+    }
+
+    /// Consume a run of digits and '_' separators.
+    fn digits(&mut self, is_digit: fn(&str) -> bool) -> String {
+        let mut digits = String::new();
+        while is_digit(self.peek()) || self.peek() == "_" {
+            digits.push_str(self.advance());
+        }
+        digits
+    }
+
+    /// Non empty, with '_' only between digits.
+    fn is_digit_group(digits: &str) -> bool {
+        !digits.is_empty()
+            && !digits.starts_with('_')
+            && !digits.ends_with('_')
+            && !digits.contains("__")
+    }
 
-        let num_str = self.source_graphemes[self.pos.start..self.pos.current].join("");
-        let value: f64 = str::parse(&num_str).expect("This should be a valid number");
-        self.add_token(TokenType::NUMBER { litral: value })
+    fn number_token(&mut self, value: f64) {
+        if value.is_finite() {
+            self.add_token(TokenType::NUMBER { litral: value })
+        } else {
+            self.error(
+                "S007",
+                "Malformed number literal.",
+                "too large to be represented",
+            )
+        }
     }
 
+    /// The lexeme is NFC normalized, so that names which only differ in how their
+    /// charactors are composed refer to the same variable.
     fn identifier(&mut self) {
         while Self::is_alpha_numeric(self.peek()) {
             self.advance();
         }
 
-        let text = self.source_graphemes[self.pos.start..self.pos.current].join("");
+        let text: String = self.source_graphemes[self.pos.start..self.pos.current]
+            .join("")
+            .nfc()
+            .collect();
         let token = match self.keywords.get(text.as_str()) {
             Some(token_type) => token_type.clone(),
             None => TokenType::IDENTIFIER,
         };
-        self.add_token(token);
+        let span = self.current_span();
+        self.last_token_line = self.pos.line;
+        self.tokens.push(Token::new(token, text, span));
+    }
+
+    fn is_new_line(c: &str) -> bool {
+        matches!(c, "\n" | "\r\n")
     }
 
     fn is_digit(c: &str) -> bool {
-        match c {
-            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => true,
-            _ => false,
-        }
+        c.len() == 1 && c.as_bytes()[0].is_ascii_digit()
     }
 
+    /// Can the grapheme start an identifier, per UAX #31 (XID_Start) or '_'.
     fn is_alpha(c: &str) -> bool {
-        match c {
-            "_" => true,
-            "a" | "b" | "c" | "d" | "e" | "f" | "g" | "h" | "i" | "j" | "k" | "l" | "m" | "n"
-            | "o" | "p" | "q" | "r" | "s" | "t" | "u" | "v" | "w" | "x" | "y" | "z" | "A" | "B"
-            | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" | "L" | "M" | "N" | "O" | "P"
-            | "Q" | "R" | "S" | "T" | "U" | "V" | "W" | "X" | "Y" | "Z" => true,
-            _ => false,
+        let mut chars = c.chars();
+        match chars.next() {
+            Some(first) => (first == '_' || is_xid_start(first)) && chars.all(is_xid_continue),
+            None => false,
         }
     }
 
+    /// Can the grapheme continue an identifier (XID_Continue).
     fn is_alpha_numeric(c: &str) -> bool {
-        Self::is_alpha(c) || Self::is_digit(c)
+        !c.is_empty() && c.chars().all(is_xid_continue)
     }
 }
 
+/// A `${` inside a string literal.
+#[derive(Clone)]
+struct Interpolation {
+    /// Braces opened inside the interpolated expression and not closed yet.
+    open_braces: usize,
+    start: Span,
+}
+
 #[allow(non_snake_case)]
 fn KEYWORDS() -> HashMap<&'static str, TokenType> {
     let keywords: HashMap<&'static str, TokenType> = [
         ("and", TokenType::AND),
+        ("break", TokenType::BREAK),
+        ("catch", TokenType::CATCH),
         ("class", TokenType::CLASS),
+        ("continue", TokenType::CONTINUE),
         ("else", TokenType::ELSE),
         ("false", TokenType::FALSE),
+        ("finally", TokenType::FINALLY),
         ("for", TokenType::FOR),
         ("fun", TokenType::FUN),
         ("if", TokenType::IF),
@@ -280,7 +715,9 @@ fn KEYWORDS() -> HashMap<&'static str, TokenType> {
         ("return", TokenType::RETURN),
         ("super", TokenType::SUPER),
         ("this", TokenType::THIS),
+        ("throw", TokenType::THROW),
         ("true", TokenType::TRUE),
+        ("try", TokenType::TRY),
         ("var", TokenType::VAR),
         ("while", TokenType::WHILE),
     ]
@@ -288,3 +725,284 @@ fn KEYWORDS() -> HashMap<&'static str, TokenType> {
     .collect();
     keywords
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenType::*;
+
+    fn token_types(source: &str) -> Vec<TokenType> {
+        Scanner::new(String::from(source))
+            .scan_tokens()
+            .expect("source should scan")
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect()
+    }
+
+    /// Code and label of every error reported for `source`.
+    fn errors(source: &str) -> Vec<(&'static str, String)> {
+        Scanner::new(String::from(source))
+            .scan_tokens()
+            .expect_err("source should not scan")
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.label.unwrap_or_default()))
+            .collect()
+    }
+
+    fn number(value: f64) -> TokenType {
+        NUMBER { litral: value }
+    }
+
+    #[test]
+    fn double_slash_ending_an_expression_is_integer_division() {
+        assert_eq!(
+            token_types("7 // 2;"),
+            vec![number(7.0), SLASH_SLASH, number(2.0), SEMICOLON, EOF]
+        );
+        assert_eq!(
+            token_types("[a // (b)]"),
+            vec![
+                LEFT_BRACKET,
+                IDENTIFIER,
+                SLASH_SLASH,
+                LEFT_PARAN,
+                IDENTIFIER,
+                RIGHT_PARAN,
+                RIGHT_BRACKET,
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn tilde_before_block_comment() {
+        assert_eq!(
+            token_types("print ~/* note */5;"),
+            vec![PRINT, TILDE, number(5.0), SEMICOLON, EOF]
+        );
+    }
+
+    #[test]
+    fn double_slash_after_closing_paren() {
+        assert_eq!(
+            token_types("f(9) // 2"),
+            vec![IDENTIFIER, LEFT_PARAN, number(9.0), RIGHT_PARAN, EOF]
+        );
+        assert_eq!(
+            token_types("print f(9) // 2;"),
+            vec![
+                PRINT,
+                IDENTIFIER,
+                LEFT_PARAN,
+                number(9.0),
+                RIGHT_PARAN,
+                SLASH_SLASH,
+                number(2.0),
+                SEMICOLON,
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn double_slash_before_text_that_does_not_scan_is_a_comment() {
+        assert_eq!(
+            token_types("x = a // don't; #1\n;"),
+            vec![IDENTIFIER, EQUAL, IDENTIFIER, SEMICOLON, EOF]
+        );
+    }
+
+    #[test]
+    fn double_slash_at_start_of_line_is_a_comment() {
+        assert_eq!(
+            token_types("var x = a\n// b;\n;"),
+            vec![VAR, IDENTIFIER, EQUAL, IDENTIFIER, SEMICOLON, EOF]
+        );
+    }
+
+    #[test]
+    fn double_slash_after_method_header_is_a_comment() {
+        let source = "class A { init() // set up\n{} }";
+        assert!(crate::compile(String::from(source), None).is_ok());
+        assert_eq!(
+            token_types(source),
+            vec![
+                CLASS,
+                IDENTIFIER,
+                LEFT_BRACE,
+                IDENTIFIER,
+                LEFT_PARAN,
+                RIGHT_PARAN,
+                LEFT_BRACE,
+                RIGHT_BRACE,
+                RIGHT_BRACE,
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn double_slash_at_end_of_line_is_a_comment() {
+        assert_eq!(
+            token_types("var x = a // note\n+ 1;"),
+            vec![
+                VAR,
+                IDENTIFIER,
+                EQUAL,
+                IDENTIFIER,
+                PLUS,
+                number(1.0),
+                SEMICOLON,
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn double_slash_inside_interpolation() {
+        assert_eq!(
+            token_types("\"${a // b}\";"),
+            vec![
+                INTERPOLATION {
+                    litral: String::new()
+                },
+                IDENTIFIER,
+                SLASH_SLASH,
+                IDENTIFIER,
+                INTERPOLATION_END,
+                STRING {
+                    litral: String::new()
+                },
+                SEMICOLON,
+                EOF
+            ]
+        );
+        // Without the end of the expression on its line, it is a comment.
+        assert_eq!(
+            token_types("\"${a // note\n} // kept\""),
+            vec![
+                INTERPOLATION {
+                    litral: String::new()
+                },
+                IDENTIFIER,
+                INTERPOLATION_END,
+                STRING {
+                    litral: String::from(" // kept")
+                },
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn number_literals() {
+        for (source, value) in [
+            ("123", 123.0),
+            ("123.45", 123.45),
+            ("0xFF", 255.0),
+            ("0Xff", 255.0),
+            ("0x1_F", 31.0),
+            ("0b1010", 10.0),
+            ("1_000_000", 1_000_000.0),
+            ("1e-9", 1e-9),
+            ("6.02e23", 6.02e23),
+            ("2E+3", 2000.0),
+        ] {
+            assert_eq!(token_types(source), vec![number(value), EOF], "{}", source);
+        }
+    }
+
+    #[test]
+    fn malformed_number_literals() {
+        let malformed = |label: &str| vec![("S007", String::from(label))];
+        assert_eq!(errors("0x"), malformed("expected hex digits after '0x'"));
+        assert_eq!(errors("0b"), malformed("expected binary digits after '0b'"));
+        assert_eq!(
+            errors("0b102"),
+            malformed("expected binary digits after '0b'")
+        );
+        assert_eq!(errors("1e"), malformed("expected digits in the exponent"));
+        assert_eq!(errors("1e+"), malformed("expected digits in the exponent"));
+        let separator = malformed("'_' can only be used between digits");
+        assert_eq!(errors("1__0"), separator);
+        assert_eq!(errors("1_"), separator);
+        assert_eq!(errors("1.5_"), separator);
+        assert_eq!(errors("0x_F"), separator);
+        assert_eq!(errors("1e999"), malformed("too large to be represented"));
+        assert_eq!(
+            errors(&format!("0x{}", "F".repeat(300))),
+            malformed("too large to be represented")
+        );
+    }
+
+    #[test]
+    fn malformed_number_literal_is_reported_over_the_whole_literal() {
+        let diagnostics = Scanner::new(String::from("x = 0b102;"))
+            .scan_tokens()
+            .expect_err("source should not scan");
+        assert_eq!((diagnostics[0].span.start, diagnostics[0].span.end), (4, 9));
+    }
+
+    #[test]
+    fn interpolation_ends_with_its_own_token() {
+        assert_eq!(
+            token_types("\"a${x}b${ {1: 2}[1] }c\""),
+            vec![
+                INTERPOLATION {
+                    litral: String::from("a")
+                },
+                IDENTIFIER,
+                INTERPOLATION_END,
+                INTERPOLATION {
+                    litral: String::from("b")
+                },
+                LEFT_BRACE,
+                number(1.0),
+                COLON,
+                number(2.0),
+                RIGHT_BRACE,
+                LEFT_BRACKET,
+                number(1.0),
+                RIGHT_BRACKET,
+                INTERPOLATION_END,
+                STRING {
+                    litral: String::from("c")
+                },
+                EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn string_continues_after_the_end_of_interpolation() {
+        let mut scanner = Scanner::new(String::from("\"${x}y\""));
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        let spans: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.lexeme.as_str(), token.span.start, token.span.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("\"${", 0, 3),
+                ("x", 3, 4),
+                ("}", 4, 5),
+                ("y\"", 5, 7),
+                ("", 7, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_interpolation() {
+        assert_eq!(
+            errors("\"${x"),
+            vec![("S006", String::from("interpolation starts here"))]
+        );
+        assert_eq!(
+            errors("\"${x}"),
+            vec![("S003", String::from("string starts here"))]
+        );
+    }
+}
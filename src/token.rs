@@ -1,17 +1,77 @@
-use std::fmt::Display;
-#[derive(PartialEq, Debug, Clone)]
+use serde::Serialize;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+/// Location of a piece of source text.
+/// `Note:` The default span (line 0) is used for nodes without a location in source.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Span {
+    /// Name of the source file, `None` for unnamed source.
+    pub file: Option<Arc<str>>,
+    /// Byte offset of the first byte.
+    pub start: usize,
+    /// Byte offset one past the last byte.
+    pub end: usize,
+    /// 1-based line of `start`.
+    pub line: usize,
+    /// 1-based column of `start`, counted in grapheme clusters.
+    pub col: usize,
+}
+
+impl Span {
+    /// Span covering both `self` and `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        if other.line == 0 {
+            return self.clone();
+        } else if self.line == 0 {
+            return other.clone();
+        }
+        let (first, last) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Span {
+            file: first.file.clone(),
+            start: first.start,
+            end: last.end.max(first.end),
+            line: first.line,
+            col: first.col,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:{}:{}", file, self.line, self.col)
+        } else {
+            write!(f, "{}:{}", self.line, self.col)
+        }
+    }
+}
+#[derive(PartialEq, Debug, Clone, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum TokenType {
     // Single charactor token
+    AMPERSAND,
+    CARET,
+    PIPE,
+    QUESTION,
+    TILDE,
     LEFT_PARAN,
     RIGHT_PARAN,
     LEFT_BRACE,
     RIGHT_BRACE,
+    LEFT_BRACKET,
+    RIGHT_BRACKET,
     COMMA,
+    COLON,
     DOT,
     MINUS,
     PLUS,
     SEMICOLON,
+    PERCENT,
     SLASH,
     STAR,
 
@@ -22,19 +82,46 @@ pub enum TokenType {
     EQUAL_EQUAL,
     GREATER,
     GREATER_EQUAL,
+    GREATER_GREATER,
     LESS,
     LESS_EQUAL,
+    LESS_LESS,
+    MINUS_EQUAL,
+    MINUS_MINUS,
+    PERCENT_EQUAL,
+    PLUS_EQUAL,
+    PLUS_PLUS,
+    QUESTION_QUESTION,
+    SLASH_EQUAL,
+    SLASH_SLASH,
+    STAR_EQUAL,
+    STAR_STAR,
 
     // Literals
     IDENTIFIER,
-    STRING { litral: String },
-    NUMBER { litral: f64 },
+    STRING {
+        litral: String,
+    },
+    /// Segment of a string literal up to a `${`, the interpolated expression follows.
+    /// The string continues with more segments and ends with a `STRING`.
+    INTERPOLATION {
+        litral: String,
+    },
+    /// The `}` closing an interpolated expression, the next segment of the string follows.
+    INTERPOLATION_END,
+    NUMBER {
+        litral: f64,
+    },
 
     // Keywords
     AND,
+    BREAK,
+    CATCH,
     CLASS,
+    CONTINUE,
     ELSE,
     FALSE,
+    FINALLY,
     FUN,
     FOR,
     IF,
@@ -44,34 +131,36 @@ pub enum TokenType {
     RETURN,
     SUPER,
     THIS,
+    THROW,
     TRUE,
+    TRY,
     VAR,
     WHILE,
 
     EOF,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Token {
+    pub fn new(token_type: TokenType, lexeme: String, span: Span) -> Token {
         Token {
             token_type,
             lexeme,
-            line,
+            span,
         }
     }
 }
 
 impl Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.token_type {
-            TokenType::STRING { litral } => {
+            TokenType::STRING { litral } | TokenType::INTERPOLATION { litral } => {
                 write!(f, "{:?} {}", self.token_type, litral)
             }
             TokenType::NUMBER { litral } => {
@@ -0,0 +1,70 @@
+use std::thread;
+
+use jlox::interpreter::Interpreter;
+use jlox::RunOutcome;
+
+/// Run `source` on a thread with the default stack size, like a host would. Fails with the
+/// message of the diagnostic.
+fn run_on_default_thread(
+    source: &'static str,
+    max_call_depth: Option<usize>,
+) -> Result<(), String> {
+    let outcome = thread::spawn(move || {
+        let mut interpreter = Interpreter::new();
+        if let Some(max_call_depth) = max_call_depth {
+            interpreter = interpreter.with_max_call_depth(max_call_depth);
+        }
+        jlox::run(String::from(source), None, &mut interpreter)
+    })
+    .join()
+    .expect("the interpreter thread should not crash");
+    match outcome {
+        RunOutcome::Success => Ok(()),
+        outcome => Err(outcome.diagnostics()[0].message.clone()),
+    }
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow_error() {
+    assert_eq!(
+        run_on_default_thread("fun f() { f(); } f();", None),
+        Err(String::from("Stack overflow."))
+    );
+}
+
+#[test]
+fn deep_recursion_with_nested_bodies_fits() {
+    let source = "
+        fun f(n) {
+            if (n > 0) { { { if (true) { return f(n - 1) + 1; } } } }
+            return 0;
+        }
+        if (f(9000) != 9000) { throw \"wrong depth\"; }";
+    assert_eq!(run_on_default_thread(source, Some(10_000)), Ok(()));
+}
+
+#[test]
+fn stack_overflow_can_be_caught() {
+    let source = "
+        fun f() { f(); }
+        var caught = false;
+        try { f(); } catch (e) { caught = e.message == \"Stack overflow.\"; }
+        if (!caught) { throw \"not caught\"; }";
+    assert_eq!(run_on_default_thread(source, None), Ok(()));
+}
+
+#[test]
+fn diagnostics_can_be_sent_back_from_the_interpreter_thread() {
+    let outcome = thread::spawn(|| {
+        jlox::run(
+            String::from("print 1 +;"),
+            Some("worker.lox"),
+            &mut Interpreter::new(),
+        )
+    })
+    .join()
+    .expect("the interpreter thread should not crash");
+    let span = &outcome.diagnostics()[0].span;
+    assert_eq!(span.file.as_deref(), Some("worker.lox"));
+    assert_eq!((span.line, span.col), (1, 10));
+}
@@ -0,0 +1,22 @@
+use jlox::interpreter::Interpreter;
+use jlox::RunOutcome;
+
+fn run(source: &str) -> RunOutcome {
+    jlox::run(String::from(source), None, &mut Interpreter::new())
+}
+
+#[test]
+fn instance_is_equal_to_this_returned_by_its_methods() {
+    let source = "
+        class A { me() { return this; } }
+        var a = A();
+        if (a.me() != a) { throw \"a.me() != a\"; }
+        if (a.me() != a.me()) { throw \"a.me() != a.me()\"; }
+        if (A() == a) { throw \"A() == a\"; }";
+    let outcome = run(source);
+    assert!(
+        matches!(outcome, RunOutcome::Success),
+        "unexpected {:?}",
+        outcome
+    );
+}
@@ -0,0 +1,94 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use jlox::interpreter::interpreter_error::Interruption;
+use jlox::interpreter::Interpreter;
+use jlox::RunOutcome;
+
+const ENDLESS_LOOP: &str = "while (true) {}";
+
+fn run(source: &str, interpreter: &mut Interpreter) -> RunOutcome {
+    jlox::run(String::from(source), None, interpreter)
+}
+
+fn assert_interrupted(outcome: &RunOutcome, reason: Interruption) {
+    match outcome {
+        RunOutcome::Interrupted(actual, diagnostic) => {
+            assert_eq!(*actual, reason);
+            assert_eq!(diagnostic.code, reason.code());
+            assert_eq!(outcome.exit_code(), 75);
+        }
+        outcome => panic!("expected {:?}, got {:?}", reason, outcome),
+    }
+}
+
+#[test]
+fn step_budget_stops_an_endless_loop() {
+    let mut interpreter = Interpreter::new().with_step_budget(10_000);
+    assert_interrupted(
+        &run(ENDLESS_LOOP, &mut interpreter),
+        Interruption::StepBudgetExhausted,
+    );
+}
+
+#[test]
+fn step_budget_lets_a_short_script_finish() {
+    let mut interpreter = Interpreter::new().with_step_budget(10_000);
+    let outcome = run("var x = 0; while (x < 10) x = x + 1;", &mut interpreter);
+    assert!(
+        matches!(outcome, RunOutcome::Success),
+        "unexpected {:?}",
+        outcome
+    );
+}
+
+#[test]
+fn deadline_stops_an_endless_loop() {
+    let deadline = Instant::now() + Duration::from_millis(50);
+    let mut interpreter = Interpreter::new().with_deadline(deadline);
+    assert_interrupted(
+        &run(ENDLESS_LOOP, &mut interpreter),
+        Interruption::DeadlineExceeded,
+    );
+    assert!(Instant::now() >= deadline);
+}
+
+#[test]
+fn cancel_handle_stops_a_running_script() {
+    let mut interpreter = Interpreter::new();
+    let cancel_handle = interpreter.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        cancel_handle.cancel();
+    });
+    assert_interrupted(
+        &run(ENDLESS_LOOP, &mut interpreter),
+        Interruption::Cancelled,
+    );
+    canceller
+        .join()
+        .expect("the cancelling thread should not crash");
+}
+
+#[test]
+fn cancelled_interpreter_stops_at_the_first_step() {
+    let mut interpreter = Interpreter::new();
+    interpreter.cancel_handle().cancel();
+    assert_interrupted(
+        &run("print \"not printed\";", &mut interpreter),
+        Interruption::Cancelled,
+    );
+}
+
+#[test]
+fn interruption_cannot_be_caught() {
+    let source = "
+        var caught = false;
+        try { while (true) {} } catch (e) { caught = true; }
+        while (!caught) {}";
+    let mut interpreter = Interpreter::new().with_step_budget(10_000);
+    assert_interrupted(
+        &run(source, &mut interpreter),
+        Interruption::StepBudgetExhausted,
+    );
+}
@@ -0,0 +1,117 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use jlox::interpreter::interpreter_error::Interruption;
+use jlox::interpreter::Interpreter;
+use jlox::RunOutcome;
+
+const MEMORY_LIMIT: usize = 4 << 20;
+
+/// Counts the bytes the process really holds, to check them against the memory limit.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Tests measuring the heap run one at a time.
+static MEASURING: Mutex<()> = Mutex::new(());
+
+/// Run `source` under `MEMORY_LIMIT`, also returns by how much the heap grew at most.
+fn run_measured(source: &str) -> (RunOutcome, usize) {
+    let _measuring = MEASURING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let source = String::from(source);
+    let mut interpreter = Interpreter::new().with_memory_limit(MEMORY_LIMIT);
+    let baseline = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(baseline, Ordering::Relaxed);
+    let outcome = jlox::run(source, None, &mut interpreter);
+    (outcome, PEAK_BYTES.load(Ordering::Relaxed) - baseline)
+}
+
+fn assert_out_of_memory(outcome: &RunOutcome) {
+    match outcome {
+        RunOutcome::Interrupted(Interruption::OutOfMemory, diagnostic) => {
+            assert_eq!(diagnostic.code, "E005")
+        }
+        outcome => panic!("expected to run out of memory, got {:?}", outcome),
+    }
+}
+
+#[test]
+fn growing_string_runs_out_of_memory() {
+    let (outcome, growth) = run_measured("var s = \"x\"; while (true) { s = s + s; }");
+    assert_out_of_memory(&outcome);
+    assert!(growth < 4 * MEMORY_LIMIT, "heap grew by {} bytes", growth);
+}
+
+#[test]
+fn out_of_memory_cannot_be_caught() {
+    let source = "
+        var xs = [];
+        try { while (true) { push(xs, \"${len(xs)}\"); } }
+        catch (e) { print \"caught\"; }
+        finally { xs = nil; }
+        print \"after\";";
+    assert_out_of_memory(&run_measured(source).0);
+}
+
+#[test]
+fn closures_share_their_declaration() {
+    let body = "x = x + 1;".repeat(200);
+    let source = format!(
+        "var fs = []; for (var i = 0; i < 20000; i = i + 1) {{ push(fs, fun (x) {{ {} return x; }}); }}",
+        body
+    );
+    let (outcome, growth) = run_measured(&source);
+    assert!(
+        matches!(outcome, RunOutcome::Success | RunOutcome::Interrupted(..)),
+        "unexpected {:?}",
+        outcome
+    );
+    assert!(growth < 4 * MEMORY_LIMIT, "heap grew by {} bytes", growth);
+}
+
+#[test]
+fn closures_created_without_end_run_out_of_memory() {
+    let source = "var fs = []; while (true) { push(fs, fun () { return fs; }); }";
+    let (outcome, growth) = run_measured(source);
+    assert_out_of_memory(&outcome);
+    assert!(growth < 4 * MEMORY_LIMIT, "heap grew by {} bytes", growth);
+}
+
+#[test]
+fn string_literals_share_their_text() {
+    let literal = "x".repeat(100 << 10);
+    let source = format!(
+        "var xs = []; for (var i = 0; i < 2000; i = i + 1) {{ push(xs, \"{}\"); }}",
+        literal
+    );
+    let (outcome, growth) = run_measured(&source);
+    assert!(
+        matches!(outcome, RunOutcome::Success),
+        "unexpected {:?}",
+        outcome
+    );
+    assert!(growth < 4 * MEMORY_LIMIT, "heap grew by {} bytes", growth);
+}